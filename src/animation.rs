@@ -0,0 +1,282 @@
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use eframe::{
+    egui::{Context, Slider, TextureHandle, TextureOptions, Ui},
+    epaint::Vec2,
+};
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    AnimationDecoder, DynamicImage, ImageFormat, RgbaImage,
+};
+
+use crate::{
+    button::ButtonKind,
+    cache::{MeteredTexture, TextureMeter},
+    icc::ColorTransform,
+    images::color_image,
+};
+
+/// Delays this short are played as 100ms, like browsers do.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(10);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// Decoded frames of an animated image.
+pub struct Frames {
+    frames: Vec<(RgbaImage, Duration)>,
+    /// times the animation is played, `None` to loop forever.
+    plays: Option<u32>,
+}
+
+impl Frames {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.frames.iter().map(|(frame, _)| frame.len()).sum()
+    }
+}
+
+/// All frames of `bytes` when they hold an animated GIF, APNG or WebP,
+/// `Ok(None)` for still images.
+pub fn decode_frames(bytes: &[u8], fmt: ImageFormat) -> Result<Option<Frames>, String> {
+    let frames = match fmt {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(bytes))
+            .and_then(|decoder| decoder.into_frames().collect_frames()),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(bytes))
+                .map_err(|err| format!("Failed to decode animation - (Reason: {err})"))?;
+            if !decoder.is_apng() {
+                return Ok(None);
+            }
+            decoder.apng().into_frames().collect_frames()
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(bytes))
+                .map_err(|err| format!("Failed to decode animation - (Reason: {err})"))?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames().collect_frames()
+        }
+        _ => return Ok(None),
+    }
+    .map_err(|err| format!("Failed to decode animation - (Reason: {err})"))?;
+    if frames.len() < 2 {
+        return Ok(None);
+    }
+    let frames = frames
+        .into_iter()
+        .map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = Duration::from_secs_f64(numer as f64 / denom.max(1) as f64 / 1000.0);
+            let delay = if delay <= MIN_FRAME_DELAY {
+                DEFAULT_FRAME_DELAY
+            } else {
+                delay
+            };
+            (frame.into_buffer(), delay)
+        })
+        .collect();
+    Ok(Some(Frames {
+        frames,
+        plays: plays(bytes, fmt),
+    }))
+}
+
+/// Times the animation should be played, read straight from the container
+/// since the decoders do not expose it. `None` loops forever.
+fn plays(bytes: &[u8], fmt: ImageFormat) -> Option<u32> {
+    let find = |tag: &[u8]| {
+        bytes
+            .windows(tag.len())
+            .position(|w| w == tag)
+            .map(|pos| &bytes[pos + tag.len()..])
+    };
+    let count = match fmt {
+        // NETSCAPE2.0 extension, without it the animation plays once, and
+        // its count is the repetitions after the first play.
+        ImageFormat::Gif => match find(b"NETSCAPE2.0") {
+            Some([3, 1, lo, hi, ..]) => match u16::from_le_bytes([*lo, *hi]) {
+                0 => 0,
+                n => n as u32 + 1,
+            },
+            _ => 1,
+        },
+        // acTL chunk: frame count then play count, both big endian.
+        ImageFormat::Png => match find(b"acTL") {
+            Some([_, _, _, _, a, b, c, d, ..]) => u32::from_be_bytes([*a, *b, *c, *d]),
+            _ => 0,
+        },
+        // ANIM chunk: chunk size, background color then loop count.
+        ImageFormat::WebP => match find(b"ANIM") {
+            Some([_, _, _, _, _, _, _, _, lo, hi, ..]) => u16::from_le_bytes([*lo, *hi]) as u32,
+            _ => 0,
+        },
+        _ => 0,
+    };
+    (count > 0).then_some(count)
+}
+
+/// Playback state of [`Frames`], frame textures are uploaded once shown.
+pub struct Playback {
+    frames: Arc<Frames>,
+    /// counted against the cache budget by `meter`.
+    textures: Vec<Option<MeteredTexture>>,
+    meter: TextureMeter,
+    current: usize,
+    playing: bool,
+    /// `egui` time the current frame was shown at.
+    shown_at: Option<f64>,
+    played: u32,
+}
+
+impl Playback {
+    pub fn new(frames: Arc<Frames>, meter: TextureMeter) -> Self {
+        Self {
+            textures: std::iter::repeat_with(|| None).take(frames.len()).collect(),
+            meter,
+            frames,
+            current: 0,
+            playing: true,
+            shown_at: None,
+            played: 0,
+        }
+    }
+
+    #[inline]
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    #[inline]
+    pub fn current_frame(&self) -> &RgbaImage {
+        &self.frames.frames[self.current].0
+    }
+
+    pub fn toggle(&mut self) {
+        if self.playing {
+            self.playing = false;
+        } else {
+            self.play();
+        }
+    }
+
+    fn play(&mut self) {
+        // a finished animation starts over.
+        if self.frames.plays.is_some_and(|plays| self.played >= plays) {
+            self.played = 0;
+            self.current = 0;
+        }
+        self.playing = true;
+        self.shown_at = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.current = 0;
+        self.played = 0;
+    }
+
+    pub fn seek(&mut self, frame: usize) {
+        self.playing = false;
+        self.current = frame.min(self.frames.len() - 1);
+    }
+
+    /// Advance to the frame due at the current time, and ask for a repaint
+    /// when the next one is.
+    pub fn update(&mut self, ctx: &Context) {
+        if !self.playing {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        let shown_at = self.shown_at.get_or_insert(now);
+        loop {
+            let delay = self.frames.frames[self.current].1.as_secs_f64();
+            if now - *shown_at < delay {
+                let left = delay - (now - *shown_at);
+                ctx.request_repaint_after(Duration::from_secs_f64(left));
+                return;
+            }
+            *shown_at += delay;
+            if self.current + 1 < self.frames.len() {
+                self.current += 1;
+                continue;
+            }
+            self.played += 1;
+            if self.frames.plays.is_some_and(|plays| self.played >= plays) {
+                self.playing = false;
+                return;
+            }
+            self.current = 0;
+            // far behind, e.g after the window was hidden, do not catch up.
+            if now - *shown_at > 1.0 {
+                *shown_at = now;
+            }
+        }
+    }
+
+    /// Texture and size of the current frame, converted by `color`. The
+    /// texture is downscaled to fit the texture size limit, the size is not.
+    pub fn texture(
+        &mut self,
+        ctx: &Context,
+        name: &str,
+        color: &ColorTransform,
+    ) -> (TextureHandle, Vec2) {
+        let (frame, _) = &self.frames.frames[self.current];
+        let size = Vec2::new(frame.width() as f32, frame.height() as f32);
+        let texture = self.textures[self.current].get_or_insert_with(|| {
+            let max_side = ctx.input(|i| i.max_texture_side);
+            let image = color_image(&DynamicImage::ImageRgba8(frame.clone()), max_side, color);
+            self.meter.track(ctx.load_texture(
+                format!("{name}#{}", self.current),
+                image,
+                TextureOptions::NEAREST,
+            ))
+        });
+        (texture.handle().clone(), size)
+    }
+
+    /// Drop the frame textures, they are uploaded again once shown.
+    pub fn clear_textures(&mut self) {
+        self.textures.iter_mut().for_each(|texture| *texture = None);
+    }
+
+    /// Play, pause, stop, step and scrub controls, returns true when the
+    /// current frame should be exported.
+    pub fn controls_ui(&mut self, ui: &mut Ui) -> bool {
+        let mut export = false;
+        ui.horizontal(|ui| {
+            let kind = if self.playing {
+                ButtonKind::Pause
+            } else {
+                ButtonKind::Play
+            };
+            if ui.button(kind.name()).on_hover_text("Space").clicked() {
+                self.toggle();
+            }
+            if ui.button(ButtonKind::Stop.name()).clicked() {
+                self.stop();
+            }
+            if ui.button(ButtonKind::Previous.name()).clicked() {
+                self.seek(self.current.checked_sub(1).unwrap_or(self.frames.len() - 1));
+            }
+            if ui.button(ButtonKind::Next.name()).clicked() {
+                self.seek((self.current + 1) % self.frames.len());
+            }
+            let mut frame = self.current;
+            let scrubber = Slider::new(&mut frame, 0..=self.frames.len() - 1)
+                .text(format!("{:?}", self.frames.frames[self.current].1));
+            if ui.add(scrubber).changed() {
+                self.seek(frame);
+            }
+            export = ui
+                .button(ButtonKind::Save.name())
+                .on_hover_text("Export this frame")
+                .clicked();
+        });
+        export
+    }
+}
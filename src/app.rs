@@ -1,16 +1,25 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use arboard::Clipboard;
 use eframe::{
     egui::{
-        panel::TopBottomSide, Button, CentralPanel, Id, Key, KeyboardShortcut, LayerId, Modifiers,
-        Order, TextStyle, TopBottomPanel, Ui, WidgetText, Window,
+        panel::TopBottomSide, Button, CentralPanel, Frame, Id, Key, KeyboardShortcut, LayerId,
+        Modifiers, Order, SidePanel, TextStyle, TopBottomPanel, Ui, WidgetText, Window,
     },
     emath::Align2,
     epaint::Color32,
 };
+use image::ImageFormat;
 
-use crate::{button::ButtonKind, images::IVImages};
+use crate::{
+    button::ButtonKind,
+    clipboard::{self, ClipboardHistory},
+    file_browser::FileBrowser,
+    images::IVImages,
+    save::SaveAsDialog,
+    slideshow::Slideshow,
+    CmdLine,
+};
 
 trait CustomMod {
     const CTRL_SHIFT: Modifiers = Modifiers {
@@ -23,13 +32,30 @@ trait CustomMod {
 }
 impl CustomMod for Modifiers {}
 
+/// Seconds the pointer rests before the toolbar hides in fullscreen.
+const IDLE_HIDE_AFTER: f64 = 2.0;
+/// Height at the bottom of the screen the pointer reveals the toolbar in.
+const TOOLBAR_REVEAL_HEIGHT: f32 = 48.0;
+
 #[rustfmt::skip]
 const SHORTCUTS_AND_BUTTONS: &[(ButtonKind, KeyboardShortcut, &str)] = &[
-    (ButtonKind::Open,      KeyboardShortcut::new(Modifiers::CTRL,       Key::O), "Open image in disk"),
-    (ButtonKind::Save,      KeyboardShortcut::new(Modifiers::CTRL,       Key::S), "Save Image in same path"),
-    (ButtonKind::SaveAs,    KeyboardShortcut::new(Modifiers::CTRL_SHIFT, Key::S), "Save Image in disk with opened filemanager"),
-    (ButtonKind::Copy,      KeyboardShortcut::new(Modifiers::CTRL,       Key::C), "Copy image from clipboard"),
-    (ButtonKind::Paste,     KeyboardShortcut::new(Modifiers::CTRL,       Key::P), "Paste Image to clipboard"),
+    (ButtonKind::Open,         KeyboardShortcut::new(Modifiers::CTRL,       Key::O),      "Open image in disk"),
+    (ButtonKind::Save,         KeyboardShortcut::new(Modifiers::CTRL,       Key::S),      "Save Image in same path"),
+    (ButtonKind::SaveAs,       KeyboardShortcut::new(Modifiers::CTRL_SHIFT, Key::S),      "Save Image in disk with opened filemanager"),
+    (ButtonKind::Copy,         KeyboardShortcut::new(Modifiers::CTRL,       Key::C),      "Copy selected region, or the whole image, to clipboard"),
+    (ButtonKind::CopyAll,      KeyboardShortcut::new(Modifiers::CTRL_SHIFT, Key::C),      "Copy the whole image unscaled to clipboard"),
+    (ButtonKind::Paste,        KeyboardShortcut::new(Modifiers::CTRL,       Key::P),      "Paste Image to clipboard"),
+    (ButtonKind::PasteHistory, KeyboardShortcut::new(Modifiers::CTRL_SHIFT, Key::P),      "Show the clipboard history"),
+    (ButtonKind::Edit,         KeyboardShortcut::new(Modifiers::CTRL,       Key::E),      "Toggle the edit panel"),
+    (ButtonKind::Filmstrip,    KeyboardShortcut::new(Modifiers::CTRL,       Key::T),      "Toggle the filmstrip"),
+    (ButtonKind::Gallery,      KeyboardShortcut::new(Modifiers::CTRL,       Key::G),      "Toggle the gallery grid"),
+    (ButtonKind::Play,         KeyboardShortcut::new(Modifiers::NONE,       Key::F5),     "Start or stop the slideshow"),
+    (ButtonKind::FullScreen,   KeyboardShortcut::new(Modifiers::NONE,       Key::F11),    "Toggle fullscreen, also with 'f'"),
+    (ButtonKind::Info,         KeyboardShortcut::new(Modifiers::CTRL,       Key::I),      "Toggle the metadata panel"),
+    (ButtonKind::Histogram,    KeyboardShortcut::new(Modifiers::CTRL,       Key::H),      "Toggle the histogram panel"),
+    (ButtonKind::Undo,         KeyboardShortcut::new(Modifiers::CTRL,       Key::Z),      "Undo last change"),
+    (ButtonKind::Redo,         KeyboardShortcut::new(Modifiers::CTRL_SHIFT, Key::Z),      "Redo last undone change"),
+    (ButtonKind::Close,        KeyboardShortcut::new(Modifiers::NONE,       Key::Delete), "Remove image from the list"),
 ];
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -39,7 +65,19 @@ enum IVAppEvent {
     Save,
     SaveAs,
     Copy,
+    CopyAll,
     Paste,
+    PasteHistory,
+    Edit,
+    Filmstrip,
+    Gallery,
+    Slideshow,
+    FullScreen,
+    Metadata,
+    Histogram,
+    Undo,
+    Redo,
+    Close,
 }
 impl From<ButtonKind> for IVAppEvent {
     fn from(value: ButtonKind) -> Self {
@@ -49,7 +87,19 @@ impl From<ButtonKind> for IVAppEvent {
             ButtonKind::Save => Save,
             ButtonKind::SaveAs => SaveAs,
             ButtonKind::Copy => Copy,
+            ButtonKind::CopyAll => CopyAll,
             ButtonKind::Paste => Paste,
+            ButtonKind::PasteHistory => PasteHistory,
+            ButtonKind::Edit => Edit,
+            ButtonKind::Filmstrip => Filmstrip,
+            ButtonKind::Gallery => Gallery,
+            ButtonKind::Play => Slideshow,
+            ButtonKind::FullScreen => FullScreen,
+            ButtonKind::Info => Metadata,
+            ButtonKind::Histogram => Histogram,
+            ButtonKind::Undo => Undo,
+            ButtonKind::Redo => Redo,
+            ButtonKind::Close => Close,
             _ => Noop,
         }
     }
@@ -57,8 +107,20 @@ impl From<ButtonKind> for IVAppEvent {
 
 pub struct IVApp<'a> {
     images: IVImages<'a>,
+    file_browser: FileBrowser,
+    save_as_dialog: SaveAsDialog,
+    /// the Save As dialog exports an animation frame instead.
+    exporting_frame: bool,
+    clipboard_history: ClipboardHistory,
     cb_ctx: Option<Clipboard>,
     kind_event: Option<IVAppEvent>,
+    show_edit_panel: bool,
+    show_filmstrip: bool,
+    show_gallery: bool,
+    show_metadata: bool,
+    show_histogram: bool,
+    slideshow: Slideshow,
+    black_background: bool,
 }
 
 pub fn bar_button_active(ui: &mut Ui, kind: ButtonKind, sc: KeyboardShortcut, desc: &str) -> bool {
@@ -69,11 +131,24 @@ pub fn bar_button_active(ui: &mut Ui, kind: ButtonKind, sc: KeyboardShortcut, de
         )
         .on_hover_text(kind.name_button_popup(desc))
         .clicked();
-    clicked || ui.input_mut(|i| i.count_and_consume_key(sc.modifiers, sc.key) > 0)
+    // keys typed into a text field are not shortcuts.
+    clicked
+        || (!ui.ctx().wants_keyboard_input()
+            && ui.input_mut(|i| i.count_and_consume_key(sc.modifiers, sc.key) > 0))
 }
 
 impl<'a> IVApp<'a> {
-    pub fn new(cc: &eframe::CreationContext, imgfiles: Vec<PathBuf>) -> Box<Self> {
+    pub fn new(cc: &eframe::CreationContext, cmd: CmdLine) -> Box<Self> {
+        let (prefetch, cache_budget) = (cmd.prefetch, cmd.cache_size * 1024 * 1024);
+        let black_background = cmd.black_background;
+        let apply_exif = !cmd.no_exif_orientation;
+        let (color_management, display_profile) =
+            (!cmd.no_color_management, cmd.display_profile.clone());
+        let mut slideshow = Slideshow::new(cmd.slideshow_options());
+        if cmd.slideshow.is_some() {
+            slideshow.start();
+        }
+        let imgfiles = cmd.get_files();
         egui_extras::install_image_loaders(&cc.egui_ctx);
         let cb_ctx = match Clipboard::new() {
             Ok(ok) => Some(ok),
@@ -82,35 +157,100 @@ impl<'a> IVApp<'a> {
                 None
             }
         };
+        let start_dir = imgfiles
+            .first()
+            .and_then(|p| {
+                if p.is_dir() {
+                    Some(p.as_path())
+                } else {
+                    p.parent()
+                }
+            })
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
         log::debug!("image_files: {imgfiles:?}");
+        let mut images = IVImages::new(imgfiles, prefetch, cache_budget);
+        images.set_apply_exif(apply_exif);
+        images.set_color_management(color_management);
+        if let Some(path) = &display_profile {
+            if let Err(err) = images.load_display_profile(path) {
+                log::error!("Failed to load display profile - (Reason: {err})");
+            }
+        }
         log::debug!("integration_info: {:#?}", cc.integration_info);
         Box::new(Self {
-            images: IVImages::new(imgfiles),
+            images,
+            file_browser: FileBrowser::new(start_dir.clone()),
+            save_as_dialog: SaveAsDialog::new(start_dir),
+            exporting_frame: false,
+            clipboard_history: ClipboardHistory::new(),
             cb_ctx,
             kind_event: None,
+            show_edit_panel: false,
+            show_filmstrip: false,
+            show_gallery: false,
+            show_metadata: false,
+            show_histogram: false,
+            slideshow,
+            black_background,
         })
     }
 
-    fn on_paste_event(&mut self) {
+    fn open_save_as(&mut self) {
+        if let Some((path, fmt)) = self.images.shown_path_and_format() {
+            self.save_as_dialog.open(path, fmt);
+            self.exporting_frame = false;
+        }
+    }
+
+    fn on_save_event(&mut self) {
+        match self.images.save() {
+            Ok(true) => (),
+            Ok(false) => self.open_save_as(),
+            Err(err) => log::error!("Failed to save image - (Reason: {err})"),
+        }
+    }
+
+    fn on_copy_event(&mut self, whole: bool) {
         if let Some(ref mut clipboard_ctx) = self.cb_ctx {
-            match clipboard_ctx.get_image() {
-                Ok(img) => self.images.extend_from_image_data(img),
-                Err(err) => {
-                    log::error!("Failed to get paste image - (Reason: {err})");
-                    match clipboard_ctx.get_text() {
-                        Ok(text) => {
-                            log::debug!("Got paste item: {text}");
-                        }
-                        Err(err) => {
-                            log::error!("Failed to get paste text - (Reason: {err})")
+            self.images.copy_to_clipboard(clipboard_ctx, whole);
+        }
+    }
+
+    fn on_paste_event(&mut self, ctx: &eframe::egui::Context) {
+        let Some(ref mut clipboard_ctx) = self.cb_ctx else {
+            return;
+        };
+        let items = match clipboard_ctx.get_image() {
+            Ok(img) => clipboard::image_data_to_item(img).into_iter().collect(),
+            Err(err) => {
+                log::error!("Failed to get paste image - (Reason: {err})");
+                match clipboard_ctx.get_text() {
+                    Ok(text) => {
+                        log::debug!("Got paste item: {text}");
+                        let items = clipboard::parse_pasted_text(&text);
+                        if items.is_empty() {
+                            log::error!("Pasted text has no image path or URI");
                         }
+                        items
+                    }
+                    Err(err) => {
+                        log::error!("Failed to get paste text - (Reason: {err})");
+                        vec![]
                     }
                 }
             }
+        };
+        // the clipboard belongs to the user, only clear it when asked to.
+        if self.clipboard_history.clear_after_paste {
             clipboard_ctx
                 .clear()
                 .unwrap_or_else(|err| log::error!("Failed to clear clipboard - (Reason: {err})"));
         }
+        self.clipboard_history.push(ctx, items.clone());
+        self.images.extend_from_pasted(items);
     }
 }
 
@@ -128,16 +268,99 @@ impl<'a> eframe::App for IVApp<'a> {
             use IVAppEvent as IVE;
             match ev {
                 IVE::Noop => (),
-                IVE::Open => {}
-                IVE::Save => {}
-                IVE::SaveAs => {}
-                IVE::Copy => frame.request_screenshot(),
-                IVE::Paste => self.on_paste_event(),
+                IVE::Open => self.file_browser.open(),
+                IVE::Save => self.on_save_event(),
+                IVE::SaveAs => self.open_save_as(),
+                IVE::Copy => self.on_copy_event(false),
+                IVE::CopyAll => self.on_copy_event(true),
+                IVE::Paste => self.on_paste_event(ctx),
+                IVE::PasteHistory => self.clipboard_history.toggle(),
+                IVE::Edit => self.show_edit_panel = !self.show_edit_panel,
+                IVE::Filmstrip => self.show_filmstrip = !self.show_filmstrip,
+                IVE::Gallery => self.show_gallery = !self.show_gallery,
+                IVE::Slideshow => self.slideshow.toggle(),
+                IVE::FullScreen => toggle_fullscreen(frame),
+                IVE::Metadata => self.show_metadata = !self.show_metadata,
+                IVE::Histogram => self.show_histogram = !self.show_histogram,
+                IVE::Undo => self.images.undo(),
+                IVE::Redo => self.images.redo(),
+                IVE::Close => self.images.remove_shown(),
             }
             self.kind_event = None;
         }
 
-        CentralPanel::default().show(ctx, |ui| {
+        if !ctx.wants_keyboard_input() {
+            if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::F)) {
+                toggle_fullscreen(frame);
+            }
+            if self.slideshow.is_running() && ctx.input(|i| i.key_pressed(Key::Escape)) {
+                self.slideshow.stop();
+            }
+        }
+        let (shown, len) = self.images.position();
+        let ready = self.images.is_shown_ready();
+        if let Some(idx) = self.slideshow.update(ctx, len, shown, ready) {
+            let crossfade = self.slideshow.options.crossfade;
+            self.images.show_with_crossfade(idx, crossfade);
+        }
+        let (shown, len) = self.images.position();
+        self.images
+            .set_upcoming(self.slideshow.upcoming(len, shown));
+        self.slideshow.sync_fullscreen(frame);
+
+        if let Some(paths) = self.file_browser.show(ctx, &mut self.images) {
+            self.images.extend_from_paths(paths);
+        }
+        if let Some(items) = self.clipboard_history.show(ctx) {
+            self.images.extend_from_pasted(items);
+        }
+        if let Some(path) = self.images.take_frame_export() {
+            self.save_as_dialog.open(Some(&path), ImageFormat::Png);
+            self.exporting_frame = true;
+        }
+        if let Some((path, options, embed_icc)) = self.save_as_dialog.show(ctx) {
+            let saved = if self.exporting_frame {
+                self.images.export_frame(path, options, embed_icc)
+            } else {
+                self.images.save_as(path, options, embed_icc)
+            };
+            if let Err(err) = saved {
+                log::error!("Failed to save image - (Reason: {err})");
+                self.save_as_dialog.set_error(err.to_string());
+            }
+        }
+
+        SidePanel::right("iv_edit_panel").show_animated(ctx, self.show_edit_panel, |ui| {
+            ui.heading(format!("{} Edit", ButtonKind::Edit));
+            ui.separator();
+            self.images.edit_ui(ui);
+        });
+
+        SidePanel::right("iv_histogram_panel").show_animated(ctx, self.show_histogram, |ui| {
+            ui.heading(format!("{} Histogram", ButtonKind::Histogram));
+            ui.separator();
+            self.images.histogram_ui(ui);
+        });
+
+        SidePanel::left("iv_metadata_panel").show_animated(ctx, self.show_metadata, |ui| {
+            ui.heading(format!("{} Metadata", ButtonKind::Info));
+            ui.separator();
+            self.images.metadata_ui(ui);
+        });
+
+        TopBottomPanel::bottom("iv_filmstrip").show_animated(ctx, self.show_filmstrip, |ui| {
+            self.images.filmstrip_ui(ui);
+        });
+
+        // the toolbar and arrows get out of the way of a fullscreen image.
+        let idle = frame.info().window_info.fullscreen && pointer_idle(ctx);
+        self.images.set_hide_arrows(idle);
+
+        let mut central = Frame::central_panel(&ctx.style());
+        if self.black_background {
+            central = central.fill(Color32::BLACK);
+        }
+        CentralPanel::default().frame(central).show(ctx, |ui| {
             preview_files_being_dropped(ui.ctx());
             ui.input_mut(|input| {
                 if !input.raw.dropped_files.is_empty() {
@@ -146,10 +369,28 @@ impl<'a> eframe::App for IVApp<'a> {
                     input.raw.dropped_files.clear();
                 }
             });
-            self.images.draw(ui);
+            if !self.show_gallery {
+                self.images.draw(ui).context_menu(|ui| {
+                    let fullscreen = frame.info().window_info.fullscreen;
+                    let label = format!("{} Fullscreen", ButtonKind::FullScreen);
+                    if ui.selectable_label(fullscreen, label).clicked() {
+                        toggle_fullscreen(frame);
+                        ui.close_menu();
+                    }
+                    ui.checkbox(&mut self.black_background, "Black background");
+                    self.images.inspector_ui(ui);
+                    ui.menu_button("Display", |ui| self.images.display_ui(ui));
+                    ui.menu_button("Color", |ui| self.images.color_ui(ui));
+                    ui.separator();
+                    self.images.orientation_ui(ui);
+                });
+                self.slideshow.controls_ui(ui);
+            } else if self.images.gallery_ui(ui) {
+                self.show_gallery = false;
+            }
         });
 
-        TopBottomPanel::new(TopBottomSide::Bottom, "iv_toppanel").show_animated(ctx, true, |ui| {
+        TopBottomPanel::new(TopBottomSide::Bottom, "iv_toppanel").show_animated(ctx, !idle, |ui| {
             eframe::egui::menu::bar(ui, |uibar| {
                 uibar.heading("IV - Image Viewer");
                 uibar.separator();
@@ -158,6 +399,8 @@ impl<'a> eframe::App for IVApp<'a> {
                         self.kind_event = Some(From::from(*kind));
                     }
                 }
+                uibar.separator();
+                self.images.zoom_ui(uibar);
             });
         });
 
@@ -168,20 +411,27 @@ impl<'a> eframe::App for IVApp<'a> {
                 ctx.inspection_ui(ui);
             });
         }
+        self.images.end_frame();
     }
+}
 
-    fn post_rendering(&mut self, _window_size_px: [u32; 2], frame: &eframe::Frame) {
-        if let Some(ss) = frame.screenshot() {
-            log::debug!(
-                "Got screenshot: ( width: {}, height: {} )",
-                ss.width(),
-                ss.height()
-            );
-            if let Some(ref mut clipboard_ctx) = self.cb_ctx {
-                self.images.copy_to_clipboard(frame, clipboard_ctx, ss);
-            }
-        }
+fn toggle_fullscreen(frame: &mut eframe::Frame) {
+    let fullscreen = frame.info().window_info.fullscreen;
+    frame.set_fullscreen(!fullscreen);
+}
+
+/// The pointer rested for a while, away from the toolbar at the bottom.
+fn pointer_idle(ctx: &eframe::egui::Context) -> bool {
+    let (rested, pos) =
+        ctx.input(|i| (i.pointer.time_since_last_movement(), i.pointer.hover_pos()));
+    if pos.is_some_and(|pos| pos.y > ctx.screen_rect().bottom() - TOOLBAR_REVEAL_HEIGHT) {
+        return false;
     }
+    if rested < IDLE_HIDE_AFTER {
+        ctx.request_repaint_after(Duration::from_secs_f64(IDLE_HIDE_AFTER - rested));
+        return false;
+    }
+    true
 }
 
 fn preview_files_being_dropped(ctx: &eframe::egui::Context) -> bool {
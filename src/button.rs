@@ -1,8 +1,9 @@
 use paste::paste;
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum ButtonKind {
+    #[default]
     Ok,
     Cancel,
     Apply,
@@ -21,6 +22,8 @@ pub enum ButtonKind {
     FullScreen,
     Random,
     Edit,
+    Filmstrip,
+    Gallery,
     Favorite,
     Unfavorite,
     Mute,
@@ -30,8 +33,14 @@ pub enum ButtonKind {
     Refresh,
     New,
     Copy,
+    CopyAll,
     Paste,
+    PasteHistory,
     Cut,
+    Undo,
+    Redo,
+    Info,
+    Histogram,
     No,
 }
 
@@ -56,6 +65,8 @@ impl ButtonKind {
             Self::FullScreen => "\u{26F6}",
             Self::Random => "\u{1F3B2}",
             Self::Edit => "\u{270F}",
+            Self::Filmstrip => "\u{1F39E}",
+            Self::Gallery => "\u{25A6}",
             Self::Favorite => "\u{2605}",
             Self::Unfavorite => "\u{2606}",
             Self::Mute => "\u{1F507}",
@@ -65,8 +76,14 @@ impl ButtonKind {
             Self::Refresh => "\u{1F503}",
             Self::New => "\u{1F5CB}",
             Self::Copy => "\u{1F5D0}",
+            Self::CopyAll => "\u{1F5D0}",
             Self::Paste => "\u{1F4CB}",
+            Self::PasteHistory => "\u{1F4DC}",
             Self::Cut => "\u{2702}",
+            Self::Undo => "\u{21BA}",
+            Self::Redo => "\u{21BB}",
+            Self::Info => "\u{2139}",
+            Self::Histogram => "\u{1F4CA}",
             Self::No => "\u{2718}",
         }
     }
@@ -85,26 +102,20 @@ impl ButtonKind {
     }
 }
 
-impl Default for ButtonKind {
-    fn default() -> Self {
-        Self::Ok
-    }
-}
-
 macro_rules!  standart_button {
     ($traits:ident {$( $name: ident),*}) => {
         pub trait $traits {
-            fn button_ext(&mut self, button_kind: ButtonKind) -> eframe::egui::Button;
-            fn small_button_ext(&mut self, button_kind: ButtonKind) -> eframe::egui::Button;
+            fn button_ext(&mut self, button_kind: ButtonKind) -> eframe::egui::Button<'_>;
+            fn small_button_ext(&mut self, button_kind: ButtonKind) -> eframe::egui::Button<'_>;
         paste!($(
             #[allow(unused)]
             #[inline(always)]
-            fn [<$name:lower _button>](&mut self) -> eframe::egui::Button {
+            fn [<$name:lower _button>](&mut self) -> eframe::egui::Button<'_> {
                 self.button_ext(ButtonKind::$name)
             }
             #[allow(unused)]
             #[inline(always)]
-            fn [<small_ $name:lower _button>](&mut self) -> eframe::egui::Button {
+            fn [<small_ $name:lower _button>](&mut self) -> eframe::egui::Button<'_> {
                 self.small_button_ext(ButtonKind::$name)
             }
         )*);
@@ -131,6 +142,8 @@ standart_button!(ButtonExt {
     FullScreen,
     Random,
     Edit,
+    Filmstrip,
+    Gallery,
     Favorite,
     Unfavorite,
     Mute,
@@ -140,20 +153,26 @@ standart_button!(ButtonExt {
     Refresh,
     New,
     Copy,
+    CopyAll,
     Paste,
+    PasteHistory,
     Cut,
+    Undo,
+    Redo,
+    Info,
+    Histogram,
     No
 });
 
 impl ButtonExt for eframe::egui::Ui {
     #[allow(unused)]
     #[inline(always)]
-    fn small_button_ext(&mut self, button_kind: ButtonKind) -> eframe::egui::Button {
+    fn small_button_ext(&mut self, button_kind: ButtonKind) -> eframe::egui::Button<'_> {
         eframe::egui::Button::new(button_kind.to_string())
     }
     #[allow(unused)]
     #[inline(always)]
-    fn button_ext(&mut self, button_kind: ButtonKind) -> eframe::egui::Button {
+    fn button_ext(&mut self, button_kind: ButtonKind) -> eframe::egui::Button<'_> {
         eframe::egui::Button::new(button_kind.to_string())
     }
 }
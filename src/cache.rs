@@ -0,0 +1,197 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+
+use eframe::egui::TextureHandle;
+use image::DynamicImage;
+
+use crate::{animation::Frames, icc::ColorTransform, orientation::Orientation};
+
+/// Identity of decoded content, so a file changed on disk is not served
+/// from the cache.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    Path(PathBuf, Option<SystemTime>),
+    Buffer(u64),
+    Uri(String),
+}
+
+impl CacheKey {
+    pub fn from_path(path: &Path) -> Self {
+        let mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        Self::Path(path.to_path_buf(), mtime)
+    }
+
+    pub fn from_buffer(bytes: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        Self::Buffer(hasher.finish())
+    }
+}
+
+/// Decoded content of an image, as kept in the cache.
+#[derive(Clone)]
+pub struct CachedImage {
+    pub image: Arc<DynamicImage>,
+    pub texture: TextureHandle,
+    pub frames: Option<Arc<Frames>>,
+    /// from the EXIF metadata of the source.
+    pub orientation: Orientation,
+    /// embedded in the source.
+    pub icc_profile: Option<Arc<[u8]>>,
+    /// conversion the texture was made with.
+    pub color: ColorTransform,
+}
+
+struct CacheEntry {
+    cached: CachedImage,
+    size: usize,
+    last_used: u64,
+}
+
+/// Bytes of the textures uploaded outside the cache entries, e.g animation
+/// frames, edit previews and display modes, while they are alive.
+#[derive(Debug, Clone, Default)]
+pub struct TextureMeter(Arc<AtomicUsize>);
+
+impl TextureMeter {
+    /// Count `texture` until the returned handle is dropped.
+    pub fn track(&self, texture: TextureHandle) -> MeteredTexture {
+        let [w, h] = texture.size();
+        let size = w * h * 4;
+        self.0.fetch_add(size, Ordering::Relaxed);
+        MeteredTexture {
+            texture,
+            size,
+            meter: self.clone(),
+        }
+    }
+
+    #[inline]
+    fn used(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Texture counted by a [`TextureMeter`].
+pub struct MeteredTexture {
+    texture: TextureHandle,
+    size: usize,
+    meter: TextureMeter,
+}
+
+impl MeteredTexture {
+    #[inline]
+    pub fn handle(&self) -> &TextureHandle {
+        &self.texture
+    }
+}
+
+impl Drop for MeteredTexture {
+    fn drop(&mut self) {
+        self.meter.0.fetch_sub(self.size, Ordering::Relaxed);
+    }
+}
+
+/// Decoded pixels and their textures, least recently used first out once
+/// they, and the textures of the meter, no longer fit in the byte budget.
+pub struct ImageCache {
+    entries: HashMap<CacheKey, CacheEntry>,
+    budget: usize,
+    used: usize,
+    tick: u64,
+    meter: TextureMeter,
+}
+
+impl ImageCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget,
+            used: 0,
+            tick: 0,
+            meter: TextureMeter::default(),
+        }
+    }
+
+    /// Meter of the textures uploaded outside the entries, they take from
+    /// the budget too.
+    #[inline]
+    pub fn meter(&self) -> &TextureMeter {
+        &self.meter
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<CachedImage> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.tick;
+        Some(entry.cached.clone())
+    }
+
+    #[inline]
+    pub fn contains(&self, key: &CacheKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: CacheKey, cached: CachedImage) {
+        self.tick += 1;
+        // raw pixels plus the rgba8 texture uploaded from them, and the
+        // frames of animations.
+        let CachedImage {
+            image,
+            texture,
+            frames,
+            ..
+        } = &cached;
+        let [w, h] = texture.size();
+        let size =
+            image.as_bytes().len() + w * h * 4 + frames.as_ref().map_or(0, |f| f.memory_size());
+        let entry = CacheEntry {
+            cached,
+            size,
+            last_used: self.tick,
+        };
+        self.used += size;
+        if let Some(old) = self.entries.insert(key, entry) {
+            self.used -= old.size;
+        }
+    }
+
+    /// Evict least recently used entries until the budget is met again,
+    /// `keep` are never evicted.
+    pub fn evict(&mut self, keep: &HashSet<&CacheKey>) {
+        while self.used + self.meter.used() > self.budget {
+            let Some(key) = self
+                .entries
+                .iter()
+                .filter(|(key, _)| !keep.contains(key))
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                log::debug!("evicting cached image: {key:?}");
+                self.used -= entry.size;
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for ImageCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImageCache")
+            .field("entries", &self.entries.len())
+            .field("used", &self.used)
+            .field("textures", &self.meter.used())
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
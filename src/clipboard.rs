@@ -0,0 +1,365 @@
+use std::{collections::VecDeque, io::Cursor, path::PathBuf, sync::Arc};
+
+use arboard::ImageData;
+use base64::Engine;
+use eframe::{
+    egui::{Button, Context, Image, Label, Layout, ScrollArea, Sense, Ui, Window},
+    emath::Align,
+    epaint::Vec2,
+};
+use image::ImageFormat;
+
+use crate::button::ButtonKind;
+
+const MAX_HISTORY_ENTRIES: usize = 16;
+const THUMBNAIL_SIZE: f32 = 48.0;
+
+/// An image reference found in text pasted from the clipboard.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PastedItem {
+    Path(PathBuf),
+    /// remote `http(s)://` image, loaded through egui's bytes loaders.
+    Uri(ImageFormat, String),
+    /// encoded image, pasted as pixels or embedded in a `data:` URI.
+    Bytes(ImageFormat, Arc<[u8]>),
+}
+
+impl PastedItem {
+    fn label(&self) -> String {
+        match self {
+            Self::Path(path) => path
+                .file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+            Self::Uri(_, uri) => uri.clone(),
+            Self::Bytes(fmt, bytes) => format!("{fmt:?} image, {} KiB", bytes.len() / 1024),
+        }
+    }
+}
+
+/// Encode the raw rgba pixels of a pasted image to PNG.
+pub fn image_data_to_item(img_data: ImageData<'_>) -> Option<PastedItem> {
+    let w = img_data.width as u32;
+    let h = img_data.height as u32;
+    let mut buffer = Cursor::new(Vec::with_capacity((w * h) as usize));
+    if let Err(err) = image::write_buffer_with_format(
+        &mut buffer,
+        &img_data.bytes,
+        w,
+        h,
+        image::ColorType::Rgba8,
+        image::ImageOutputFormat::Png,
+    ) {
+        log::error!("Failed to convert image from raw rgba to png - (Reason: {err})");
+        return None;
+    }
+    Some(PastedItem::Bytes(
+        ImageFormat::Png,
+        buffer.into_inner().into(),
+    ))
+}
+
+/// Parse pasted text as newline separated paths, `file://` URIs, `data:`
+/// URIs and `http(s)://` URLs, lines that are none of those are skipped.
+pub fn parse_pasted_text(text: &str) -> Vec<PastedItem> {
+    text.lines()
+        .map(|line| line.trim().trim_matches('"'))
+        // `text/uri-list` allows comment lines.
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let item = parse_line(line);
+            if item.is_none() {
+                log::debug!("skipping pasted line: {line}");
+            }
+            item
+        })
+        .collect()
+}
+
+fn parse_line(line: &str) -> Option<PastedItem> {
+    if let Some(rest) = line.strip_prefix("file://") {
+        // the host part is empty or `localhost` for local files.
+        let path = &rest[rest.find('/')?..];
+        return Some(PastedItem::Path(percent_decode_path(path)));
+    }
+    if let Some(rest) = line.strip_prefix("data:") {
+        return parse_data_uri(rest);
+    }
+    if line.starts_with("http://") || line.starts_with("https://") {
+        let path = line.split(['?', '#']).next().unwrap_or(line);
+        let fmt = ImageFormat::from_path(path).ok()?;
+        return Some(PastedItem::Uri(fmt, line.to_owned()));
+    }
+    Some(PastedItem::Path(PathBuf::from(line)))
+}
+
+/// Parse what follows `data:`, only base64 encoded images are accepted.
+fn parse_data_uri(rest: &str) -> Option<PastedItem> {
+    let (meta, data) = rest.split_once(',')?;
+    let mut params = meta.split(';');
+    let mime = params.next()?;
+    if !mime.starts_with("image/") || !params.any(|p| p == "base64") {
+        return None;
+    }
+    let bytes = match base64::engine::general_purpose::STANDARD.decode(data.trim()) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            log::error!("Failed to decode pasted data URI - (Reason: {err})");
+            return None;
+        }
+    };
+    let fmt = ImageFormat::from_mime_type(mime).or_else(|| image::guess_format(&bytes).ok())?;
+    Some(PastedItem::Bytes(fmt, bytes.into()))
+}
+
+fn percent_decode_path(path: &str) -> PathBuf {
+    let bytes = percent_encoding::percent_decode_str(path).collect::<Vec<u8>>();
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        PathBuf::from(std::ffi::OsString::from_vec(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        // `file:///C:/dir` keeps a leading slash before the drive letter.
+        let path = String::from_utf8_lossy(&bytes);
+        let path = match path.as_bytes() {
+            [b'/', _, b':', ..] => &path[1..],
+            _ => &path[..],
+        };
+        PathBuf::from(path)
+    }
+}
+
+struct HistoryEntry {
+    id: usize,
+    items: Vec<PastedItem>,
+}
+
+impl HistoryEntry {
+    fn thumbnail_uri(&self) -> Option<String> {
+        match self.items.first()? {
+            PastedItem::Path(path) => Some(format!("file://{}", path.display())),
+            PastedItem::Uri(_, uri) => Some(uri.clone()),
+            PastedItem::Bytes(..) => Some(format!("bytes://clipboard-{}", self.id)),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self.items.as_slice() {
+            [item] => item.label(),
+            [first, ..] => format!("{} and {} more", first.label(), self.items.len() - 1),
+            [] => String::new(),
+        }
+    }
+}
+
+/// What was pasted during this session, so it can be inserted again without
+/// copying it to the clipboard a second time.
+pub struct ClipboardHistory {
+    open: bool,
+    entries: VecDeque<HistoryEntry>,
+    next_id: usize,
+    /// clear the system clipboard once its content was pasted.
+    pub clear_after_paste: bool,
+}
+
+impl ClipboardHistory {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            entries: VecDeque::with_capacity(MAX_HISTORY_ENTRIES),
+            next_id: 0,
+            clear_after_paste: false,
+        }
+    }
+
+    #[inline]
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Remember pasted items, moving them to the front when pasted before.
+    pub fn push(&mut self, ctx: &Context, items: Vec<PastedItem>) {
+        if items.is_empty() {
+            return;
+        }
+        if let Some(pos) = self.entries.iter().position(|e| e.items == items) {
+            let entry = self.entries.remove(pos).expect("position is in bounds");
+            self.entries.push_front(entry);
+            return;
+        }
+        self.entries.push_front(HistoryEntry {
+            id: self.next_id,
+            items,
+        });
+        self.next_id += 1;
+        while self.entries.len() > MAX_HISTORY_ENTRIES {
+            if let Some(entry) = self.entries.pop_back() {
+                forget_thumbnail(ctx, &entry);
+            }
+        }
+    }
+
+    /// Draw the history window if it is open, returning the items of the
+    /// entry the user chose to insert again.
+    pub fn show(&mut self, ctx: &Context) -> Option<Vec<PastedItem>> {
+        if !self.open {
+            return None;
+        }
+        let mut open = self.open;
+        let mut chosen = None;
+        let mut removed = None;
+        Window::new(format!("{} Clipboard history", ButtonKind::Paste))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_size(Vec2::new(360.0, 420.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.clear_after_paste, "Clear clipboard after paste");
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if ui
+                            .add_enabled(
+                                !self.entries.is_empty(),
+                                Button::new(ButtonKind::Delete.name()),
+                            )
+                            .on_hover_text("Forget every entry")
+                            .clicked()
+                        {
+                            self.entries
+                                .drain(..)
+                                .for_each(|entry| forget_thumbnail(ctx, &entry));
+                        }
+                    });
+                });
+                ui.separator();
+                if self.entries.is_empty() {
+                    ui.weak("Nothing pasted yet");
+                    return;
+                }
+                ScrollArea::vertical().show(ui, |ui| {
+                    for (idx, entry) in self.entries.iter().enumerate() {
+                        match entry_ui(ui, entry) {
+                            Some(true) => chosen = Some(entry.items.clone()),
+                            Some(false) => removed = Some(idx),
+                            None => (),
+                        }
+                    }
+                });
+            });
+        if let Some(entry) = removed.and_then(|idx| self.entries.remove(idx)) {
+            forget_thumbnail(ctx, &entry);
+        }
+        self.open = open;
+        chosen
+    }
+}
+
+/// Row of a history entry, `Some(true)` when it should be inserted again and
+/// `Some(false)` when it should be forgotten.
+fn entry_ui(ui: &mut Ui, entry: &HistoryEntry) -> Option<bool> {
+    let mut action = None;
+    ui.push_id(entry.id, |ui| {
+        ui.horizontal(|ui| {
+            let image = match (entry.items.first(), entry.thumbnail_uri()) {
+                (Some(PastedItem::Bytes(_, bytes)), Some(uri)) => {
+                    Some(Image::from_bytes(uri, bytes.clone()))
+                }
+                (_, Some(uri)) => Some(Image::new(uri)),
+                _ => None,
+            };
+            if let Some(image) = image {
+                let res = ui.add(
+                    image
+                        .fit_to_exact_size(Vec2::splat(THUMBNAIL_SIZE))
+                        .show_loading_spinner(true)
+                        .sense(Sense::click()),
+                );
+                if res.double_clicked() {
+                    action = Some(true);
+                }
+            }
+            ui.add(Label::new(entry.label()).truncate(true));
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                if ui
+                    .small_button(ButtonKind::Delete.name())
+                    .on_hover_text("Forget this entry")
+                    .clicked()
+                {
+                    action = Some(false);
+                }
+                if ui
+                    .small_button(ButtonKind::Paste.name())
+                    .on_hover_text("Insert again")
+                    .clicked()
+                {
+                    action = Some(true);
+                }
+            });
+        });
+    });
+    action
+}
+
+fn forget_thumbnail(ctx: &Context, entry: &HistoryEntry) {
+    // remote images stay cached, they may still be shown in the viewer.
+    if let Some(PastedItem::Bytes(..)) = entry.items.first() {
+        if let Some(uri) = entry.thumbnail_uri() {
+            ctx.forget_image(&uri);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_paths_and_file_uris() {
+        let items =
+            parse_pasted_text("/tmp/a.png\nfile:///tmp/b%20c.jpg\nfile://localhost/tmp/d.gif");
+        assert_eq!(
+            items,
+            vec![
+                PastedItem::Path(PathBuf::from("/tmp/a.png")),
+                PastedItem::Path(PathBuf::from("/tmp/b c.jpg")),
+                PastedItem::Path(PathBuf::from("/tmp/d.gif")),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_blank_lines_comments_and_quotes() {
+        let items = parse_pasted_text("# uri-list comment\n\n  \"/tmp/a.png\"  \r\n");
+        assert_eq!(items, vec![PastedItem::Path(PathBuf::from("/tmp/a.png"))]);
+    }
+
+    #[test]
+    fn keeps_http_images_only() {
+        let items = parse_pasted_text(
+            "https://example.com/a.webp?size=2#top\nhttps://example.com/page.html",
+        );
+        assert_eq!(
+            items,
+            vec![PastedItem::Uri(
+                ImageFormat::WebP,
+                "https://example.com/a.webp?size=2#top".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn decodes_base64_data_uris() {
+        let png = b"\x89PNG\r\n\x1a\n";
+        let data = base64::engine::general_purpose::STANDARD.encode(png);
+        let items = parse_pasted_text(&format!("data:image/png;base64,{data}"));
+        assert_eq!(
+            items,
+            vec![PastedItem::Bytes(ImageFormat::Png, Arc::from(&png[..]))]
+        );
+        assert!(parse_pasted_text("data:image/png,raw").is_empty());
+        assert!(parse_pasted_text("data:text/plain;base64,aGk=").is_empty());
+    }
+}
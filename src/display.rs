@@ -0,0 +1,377 @@
+use eframe::{
+    egui::{Painter, Slider, Ui},
+    epaint::{Color32, ColorImage, Rect, Rounding, Vec2},
+};
+use image::DynamicImage;
+
+use crate::icc::ColorTransform;
+
+/// Side of a checkerboard square, in points.
+const CHECKER_SIDE: f32 = 12.0;
+const CHECKER_LIGHT: Color32 = Color32::from_gray(204);
+const CHECKER_DARK: Color32 = Color32::from_gray(153);
+
+/// Channels of the image that are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelMode {
+    #[default]
+    Rgba,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    /// Rec. 709 luma of the colour channels.
+    Luminance,
+}
+
+impl ChannelMode {
+    pub const ALL: [(Self, &'static str); 6] = [
+        (Self::Rgba, "RGBA"),
+        (Self::Red, "Red"),
+        (Self::Green, "Green"),
+        (Self::Blue, "Blue"),
+        (Self::Alpha, "Alpha"),
+        (Self::Luminance, "Luminance"),
+    ];
+}
+
+/// How transparent pixels are previewed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// blended over the background.
+    #[default]
+    Blend,
+    /// colour channels as stored, alpha ignored, to reveal the colour kept
+    /// under transparent pixels.
+    Straight,
+    /// colour channels multiplied by alpha, i.e over black.
+    Premultiplied,
+}
+
+impl AlphaMode {
+    pub const ALL: [(Self, &'static str); 3] = [
+        (Self::Blend, "Blend"),
+        (Self::Straight, "Straight"),
+        (Self::Premultiplied, "Premultiplied"),
+    ];
+}
+
+/// Curve compressing the exposed linear values into the displayable range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToneMap {
+    #[default]
+    Clamp,
+    Reinhard,
+    /// Narkowicz fit of the ACES filmic curve.
+    AcesFilmic,
+}
+
+impl ToneMap {
+    pub const ALL: [(Self, &'static str); 3] = [
+        (Self::Clamp, "Clamp"),
+        (Self::Reinhard, "Reinhard"),
+        (Self::AcesFilmic, "ACES filmic"),
+    ];
+
+    fn apply(self, v: f32) -> f32 {
+        match self {
+            Self::Clamp => v,
+            Self::Reinhard => v / (1.0 + v),
+            Self::AcesFilmic => (v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14),
+        }
+        .clamp(0.0, 1.0)
+    }
+}
+
+/// Mapping of the pixel values to the screen. Float images hold linear
+/// values, the others are decoded from `gamma` first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    /// stops the linear values are scaled by.
+    pub exposure: f32,
+    pub gamma: f32,
+    pub tone_map: ToneMap,
+    /// the shown channel, luminance for RGBA, stretched over its range in
+    /// the image and coloured from blue to red, for data like depth maps.
+    pub false_colour: bool,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            gamma: 2.2,
+            tone_map: ToneMap::default(),
+            false_colour: false,
+        }
+    }
+}
+
+/// What is painted behind the image, seen through its transparent pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Background {
+    /// the panel background.
+    #[default]
+    None,
+    Checkerboard,
+    Solid,
+}
+
+/// How the shown image is displayed, its pixels are left alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayOptions {
+    pub channels: ChannelMode,
+    pub alpha: AlphaMode,
+    pub background: Background,
+    /// colour of [`Background::Solid`].
+    pub solid: Color32,
+    pub tone: Tone,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            channels: ChannelMode::default(),
+            alpha: AlphaMode::default(),
+            background: Background::default(),
+            solid: Color32::WHITE,
+            tone: Tone::default(),
+        }
+    }
+}
+
+impl DisplayOptions {
+    /// The pixels are shown as decoded, no texture transform is needed.
+    /// Float images are tone mapped with the default [`Tone`] when decoded.
+    #[inline]
+    pub fn is_plain(&self) -> bool {
+        self.channels == ChannelMode::Rgba
+            && self.alpha == AlphaMode::Blend
+            && self.tone == Tone::default()
+    }
+
+    /// [`DisplayOptions::transform`] gives the same result with `other`.
+    pub fn same_transform(&self, other: &Self) -> bool {
+        self.channels == other.channels && self.alpha == other.alpha && self.tone == other.tone
+    }
+
+    /// Texture of `image` with the tone, channel and alpha modes applied,
+    /// scaled down to fit `max_side`. Colours go through `color`, channels
+    /// shown alone are left as they are.
+    pub fn transform(
+        &self,
+        image: &DynamicImage,
+        max_side: usize,
+        color: &ColorTransform,
+    ) -> ColorImage {
+        let float = is_float(image);
+        let max_side = max_side as u32;
+        let rgba = if image.width() > max_side || image.height() > max_side {
+            image
+                .resize(max_side, max_side, image::imageops::FilterType::Triangle)
+                .into_rgba32f()
+        } else {
+            image.to_rgba32f()
+        };
+        let size = [rgba.width() as usize, rgba.height() as usize];
+        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        if self.channels == ChannelMode::Alpha {
+            // alpha is coverage, shown as an opaque mask without exposure,
+            // tone mapping or false colour.
+            let bytes = rgba
+                .pixels()
+                .flat_map(|pixel| {
+                    let a = to_u8(pixel.0[3]);
+                    [a, a, a, u8::MAX]
+                })
+                .collect::<Vec<_>>();
+            return ColorImage::from_rgba_unmultiplied(size, &bytes);
+        }
+        let Tone {
+            exposure,
+            gamma,
+            tone_map,
+            false_colour,
+        } = self.tone;
+        let scale = exposure.exp2();
+        let decode = |c: f32| if float { c } else { c.max(0.0).powf(gamma) };
+        let encode = |c: f32| tone_map.apply(c).powf(gamma.recip());
+        // exposed linear values of the shown channels, then their alpha.
+        let pixels = rgba.pixels().map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            let [r, g, b] = [r, g, b].map(|c| decode(c) * scale);
+            let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            match self.channels {
+                ChannelMode::Rgba => ([r, g, b], a),
+                ChannelMode::Red => ([r; 3], a),
+                ChannelMode::Green => ([g; 3], a),
+                ChannelMode::Blue => ([b; 3], a),
+                ChannelMode::Luminance => ([luma; 3], a),
+                ChannelMode::Alpha => unreachable!("alpha masks are written above"),
+            }
+        });
+        let shown = if false_colour {
+            let values = pixels
+                .map(|([r, g, b], a)| (0.2126 * r + 0.7152 * g + 0.0722 * b, a))
+                .collect::<Vec<_>>();
+            let (lo, hi) = values
+                .iter()
+                .map(|(v, _)| *v)
+                .filter(|v| v.is_finite())
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(v), hi.max(v))
+                });
+            let range = (hi - lo).max(f32::EPSILON);
+            values
+                .into_iter()
+                .map(|(v, a)| (turbo(((v - lo) / range).clamp(0.0, 1.0)), a))
+                .collect::<Vec<_>>()
+        } else {
+            pixels
+                .map(|(rgb, a)| (rgb.map(encode), a))
+                .collect::<Vec<_>>()
+        };
+        let mut bytes = Vec::with_capacity(shown.len() * 4);
+        for ([r, g, b], a) in shown {
+            let a = a.clamp(0.0, 1.0);
+            let pixel = match self.alpha {
+                AlphaMode::Blend => [r, g, b, a],
+                AlphaMode::Straight => [r, g, b, 1.0],
+                AlphaMode::Premultiplied => [r * a, g * a, b * a, 1.0],
+            };
+            bytes.extend(pixel.map(to_u8));
+        }
+        if self.channels == ChannelMode::Rgba && !false_colour {
+            color.apply(&mut bytes);
+        }
+        ColorImage::from_rgba_unmultiplied(size, &bytes)
+    }
+
+    /// Paint the background behind an image shown in `rect` at `opacity`.
+    pub fn paint_background(&self, painter: &Painter, rect: Rect, opacity: f32) {
+        let (light, dark) = (
+            CHECKER_LIGHT.gamma_multiply(opacity),
+            CHECKER_DARK.gamma_multiply(opacity),
+        );
+        match self.background {
+            Background::None => (),
+            Background::Solid => {
+                painter.rect_filled(rect, Rounding::ZERO, self.solid.gamma_multiply(opacity))
+            }
+            Background::Checkerboard => {
+                painter.rect_filled(rect, Rounding::ZERO, light);
+                // only the squares on screen, anchored to the image so they
+                // move along with it.
+                let visible = rect.intersect(painter.clip_rect());
+                if !visible.is_positive() {
+                    return;
+                }
+                let first = ((visible.min - rect.min) / CHECKER_SIDE).floor();
+                let last = ((visible.max - rect.min) / CHECKER_SIDE).ceil();
+                for y in first.y as i64..last.y as i64 {
+                    for x in first.x as i64..last.x as i64 {
+                        if (x + y) % 2 == 0 {
+                            continue;
+                        }
+                        let min = rect.min + Vec2::new(x as f32, y as f32) * CHECKER_SIDE;
+                        let square = Rect::from_min_size(min, Vec2::splat(CHECKER_SIDE));
+                        painter.rect_filled(square.intersect(rect), Rounding::ZERO, dark);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn options_ui(&mut self, ui: &mut Ui) {
+        let tone = &mut self.tone;
+        ui.horizontal(|ui| {
+            ui.label("Tone");
+            if ui.button("Reset").clicked() {
+                *tone = Tone::default();
+            }
+        });
+        ui.add(
+            Slider::new(&mut tone.exposure, -10.0..=10.0)
+                .step_by(0.1)
+                .suffix(" EV")
+                .text("Exposure"),
+        );
+        ui.add(
+            Slider::new(&mut tone.gamma, 1.0..=3.0)
+                .step_by(0.05)
+                .text("Gamma"),
+        );
+        ui.horizontal(|ui| {
+            for (tone_map, name) in ToneMap::ALL {
+                ui.selectable_value(&mut tone.tone_map, tone_map, name);
+            }
+        });
+        ui.checkbox(&mut tone.false_colour, "False colour")
+            .on_hover_text("Shown channel stretched over its range, blue to red");
+        ui.separator();
+        ui.label("Channels");
+        ui.horizontal_wrapped(|ui| {
+            for (mode, name) in ChannelMode::ALL {
+                ui.selectable_value(&mut self.channels, mode, name);
+            }
+        });
+        ui.label("Alpha");
+        ui.horizontal(|ui| {
+            for (mode, name) in AlphaMode::ALL {
+                ui.selectable_value(&mut self.alpha, mode, name);
+            }
+        });
+        ui.label("Background");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.background, Background::None, "None");
+            ui.selectable_value(
+                &mut self.background,
+                Background::Checkerboard,
+                "Checkerboard",
+            );
+            ui.selectable_value(&mut self.background, Background::Solid, "Solid");
+            if self.background == Background::Solid {
+                ui.color_edit_button_srgba(&mut self.solid);
+            }
+        });
+    }
+}
+
+/// Float pixels hold linear, possibly out of range, values.
+pub fn is_float(image: &DynamicImage) -> bool {
+    matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    )
+}
+
+/// Turbo colormap, polynomial fit by Google, `t` in `0..=1`.
+fn turbo(t: f32) -> [f32; 3] {
+    let poly = |c: [f32; 6]| c[0] + t * (c[1] + t * (c[2] + t * (c[3] + t * (c[4] + t * c[5]))));
+    [
+        poly([
+            0.135_721_38,
+            4.615_392_6,
+            -42.660_32,
+            132.131_08,
+            -152.942_4,
+            59.286_38,
+        ]),
+        poly([
+            0.091_402_61,
+            2.194_188_4,
+            4.842_966_6,
+            -14.185_033,
+            4.277_298_6,
+            2.829_566,
+        ]),
+        poly([
+            0.106_673_3,
+            12.641_946,
+            -60.582_05,
+            110.362_77,
+            -89.903_11,
+            27.348_25,
+        ]),
+    ]
+}
@@ -0,0 +1,425 @@
+use std::sync::{mpsc::TryRecvError, Arc};
+
+use eframe::{
+    egui::{
+        Button, ComboBox, Context, Layout, ScrollArea, Slider, TextureHandle, TextureOptions, Ui,
+    },
+    emath::Align,
+    epaint::{ColorImage, Vec2},
+};
+use image::{imageops::FilterType, DynamicImage};
+
+use crate::{
+    button::ButtonKind,
+    cache::{MeteredTexture, TextureMeter},
+    icc::ColorTransform,
+    images::color_image,
+    loader::{JobHandle, WorkerPool},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kernel3x3 {
+    Sharpen,
+    EdgeDetect,
+    Emboss,
+    BoxBlur,
+    Gaussian,
+}
+
+impl Kernel3x3 {
+    const ALL: [Kernel3x3; 5] = [
+        Self::Sharpen,
+        Self::EdgeDetect,
+        Self::Emboss,
+        Self::BoxBlur,
+        Self::Gaussian,
+    ];
+
+    #[rustfmt::skip]
+    fn values(self) -> [f32; 9] {
+        match self {
+            Self::Sharpen    => [ 0.0, -1.0,  0.0, -1.0, 5.0, -1.0,  0.0, -1.0, 0.0],
+            Self::EdgeDetect => [-1.0, -1.0, -1.0, -1.0, 8.0, -1.0, -1.0, -1.0, -1.0],
+            Self::Emboss     => [-2.0, -1.0,  0.0, -1.0, 1.0,  1.0,  0.0,  1.0, 2.0],
+            Self::BoxBlur    => [ 1.0,  1.0,  1.0,  1.0, 1.0,  1.0,  1.0,  1.0, 1.0],
+            Self::Gaussian   => [ 1.0,  2.0,  1.0,  2.0, 4.0,  2.0,  1.0,  2.0, 1.0],
+        }
+    }
+}
+
+/// A single non-destructive edit, the stack of those is only baked into the
+/// pixels when the image is saved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditOp {
+    Blur {
+        sigma: f32,
+    },
+    Brighten {
+        value: i32,
+    },
+    HueRotate {
+        degrees: i32,
+    },
+    Contrast {
+        contrast: f32,
+    },
+    /// edges trimmed away, as fractions of the width and height.
+    Crop {
+        left: f32,
+        top: f32,
+        right: f32,
+        bottom: f32,
+    },
+    Filter3x3 {
+        kernel: Kernel3x3,
+    },
+    FlipHorizontal,
+    FlipVertical,
+    Grayscale,
+    Invert,
+    Resize {
+        factor: f32,
+        filter: FilterType,
+    },
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Unsharpen {
+        sigma: f32,
+        threshold: i32,
+    },
+}
+
+impl EditOp {
+    pub const ALL: &'static [EditOp] = &[
+        Self::Blur { sigma: 2.0 },
+        Self::Brighten { value: 20 },
+        Self::HueRotate { degrees: 90 },
+        Self::Contrast { contrast: 20.0 },
+        Self::Crop {
+            left: 0.1,
+            top: 0.1,
+            right: 0.1,
+            bottom: 0.1,
+        },
+        Self::Filter3x3 {
+            kernel: Kernel3x3::Sharpen,
+        },
+        Self::FlipHorizontal,
+        Self::FlipVertical,
+        Self::Grayscale,
+        Self::Invert,
+        Self::Resize {
+            factor: 0.5,
+            filter: FilterType::Lanczos3,
+        },
+        Self::Rotate90,
+        Self::Rotate180,
+        Self::Rotate270,
+        Self::Unsharpen {
+            sigma: 2.0,
+            threshold: 10,
+        },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Blur { .. } => "Blur",
+            Self::Brighten { .. } => "Brighten",
+            Self::HueRotate { .. } => "Hue rotate",
+            Self::Contrast { .. } => "Contrast",
+            Self::Crop { .. } => "Crop",
+            Self::Filter3x3 { .. } => "Filter 3x3",
+            Self::FlipHorizontal => "Flip horizontal",
+            Self::FlipVertical => "Flip vertical",
+            Self::Grayscale => "Grayscale",
+            Self::Invert => "Invert",
+            Self::Resize { .. } => "Resize",
+            Self::Rotate90 => "Rotate 90°",
+            Self::Rotate180 => "Rotate 180°",
+            Self::Rotate270 => "Rotate 270°",
+            Self::Unsharpen { .. } => "Unsharpen",
+        }
+    }
+
+    /// Size of the result of the operation on an image of `width` by
+    /// `height` pixels.
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (w, h) = (width as f32, height as f32);
+        match *self {
+            Self::Crop {
+                left,
+                top,
+                right,
+                bottom,
+            } => (
+                (w * (1.0 - left - right)).max(1.0) as u32,
+                (h * (1.0 - top - bottom)).max(1.0) as u32,
+            ),
+            Self::Resize { factor, .. } => (
+                (w * factor).round().max(1.0) as u32,
+                (h * factor).round().max(1.0) as u32,
+            ),
+            Self::Rotate90 | Self::Rotate270 => (height, width),
+            _ => (width, height),
+        }
+    }
+
+    /// The same operation on a copy of the image downscaled by `scale`,
+    /// radii in pixels shrink along.
+    fn scaled(self, scale: f32) -> Self {
+        match self {
+            Self::Blur { sigma } => Self::Blur {
+                sigma: sigma * scale,
+            },
+            Self::Unsharpen { sigma, threshold } => Self::Unsharpen {
+                sigma: sigma * scale,
+                threshold,
+            },
+            op => op,
+        }
+    }
+
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        let (w, h) = (image.width() as f32, image.height() as f32);
+        let (width, height) = self.output_size(image.width(), image.height());
+        match *self {
+            Self::Blur { sigma } => image.blur(sigma),
+            Self::Brighten { value } => image.brighten(value),
+            Self::HueRotate { degrees } => image.huerotate(degrees),
+            Self::Contrast { contrast } => image.adjust_contrast(contrast),
+            Self::Crop { left, top, .. } => {
+                image.crop_imm((w * left) as u32, (h * top) as u32, width, height)
+            }
+            Self::Filter3x3 { kernel } => image.filter3x3(&kernel.values()),
+            Self::FlipHorizontal => image.fliph(),
+            Self::FlipVertical => image.flipv(),
+            Self::Grayscale => image.grayscale(),
+            Self::Invert => {
+                let mut image = image;
+                image.invert();
+                image
+            }
+            Self::Resize { filter, .. } => image.resize_exact(width, height, filter),
+            Self::Rotate90 => image.rotate90(),
+            Self::Rotate180 => image.rotate180(),
+            Self::Rotate270 => image.rotate270(),
+            Self::Unsharpen { sigma, threshold } => image.unsharpen(sigma, threshold),
+        }
+    }
+
+    /// Parameter sliders of the operation, returns true when any changed.
+    fn ui(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        match self {
+            Self::Blur { sigma } => {
+                changed |= ui
+                    .add(Slider::new(sigma, 0.1..=20.0).text("Sigma"))
+                    .changed();
+            }
+            Self::Brighten { value } => {
+                changed |= ui
+                    .add(Slider::new(value, -255..=255).text("Value"))
+                    .changed();
+            }
+            Self::HueRotate { degrees } => {
+                changed |= ui
+                    .add(Slider::new(degrees, -180..=180).text("Degrees"))
+                    .changed();
+            }
+            Self::Contrast { contrast } => {
+                changed |= ui
+                    .add(Slider::new(contrast, -100.0..=100.0).text("Factor"))
+                    .changed();
+            }
+            Self::Crop {
+                left,
+                top,
+                right,
+                bottom,
+            } => {
+                for (value, text) in [
+                    (left, "Left"),
+                    (top, "Top"),
+                    (right, "Right"),
+                    (bottom, "Bottom"),
+                ] {
+                    changed |= ui.add(Slider::new(value, 0.0..=0.49).text(text)).changed();
+                }
+            }
+            Self::Filter3x3 { kernel } => {
+                ComboBox::from_label("Kernel")
+                    .selected_text(format!("{kernel:?}"))
+                    .show_ui(ui, |ui| {
+                        for k in Kernel3x3::ALL {
+                            changed |= ui.selectable_value(kernel, k, format!("{k:?}")).changed();
+                        }
+                    });
+            }
+            Self::Resize { factor, filter } => {
+                changed |= ui
+                    .add(Slider::new(factor, 0.05..=4.0).text("Factor"))
+                    .changed();
+                ComboBox::from_label("Filter")
+                    .selected_text(format!("{filter:?}"))
+                    .show_ui(ui, |ui| {
+                        for f in [
+                            FilterType::Nearest,
+                            FilterType::Triangle,
+                            FilterType::CatmullRom,
+                            FilterType::Gaussian,
+                            FilterType::Lanczos3,
+                        ] {
+                            changed |= ui.selectable_value(filter, f, format!("{f:?}")).changed();
+                        }
+                    });
+            }
+            Self::Unsharpen { sigma, threshold } => {
+                changed |= ui
+                    .add(Slider::new(sigma, 0.1..=20.0).text("Sigma"))
+                    .changed();
+                changed |= ui
+                    .add(Slider::new(threshold, 0..=255).text("Threshold"))
+                    .changed();
+            }
+            _ => (),
+        }
+        changed
+    }
+}
+
+/// Bake the whole operation stack into a copy of `image`.
+pub fn apply_all(ops: &[EditOp], image: &DynamicImage) -> DynamicImage {
+    ops.iter().fold(image.clone(), |image, op| op.apply(image))
+}
+
+/// Draw the operation stack of an image, returns true when it changed.
+pub fn edit_stack_ui(ui: &mut Ui, ops: &mut Vec<EditOp>) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.menu_button("\u{2795} Add", |ui| {
+            for op in EditOp::ALL {
+                if ui.button(op.name()).clicked() {
+                    ops.push(*op);
+                    changed = true;
+                    ui.close_menu();
+                }
+            }
+        });
+        if ui
+            .add_enabled(!ops.is_empty(), Button::new(ButtonKind::Reset.name()))
+            .on_hover_text("Remove every operation")
+            .clicked()
+        {
+            ops.clear();
+            changed = true;
+        }
+    });
+    ui.separator();
+
+    let mut moved = None;
+    let mut removed = None;
+    ScrollArea::vertical()
+        .id_source("edit_stack_scroll")
+        .show(ui, |ui| {
+            for (idx, op) in ops.iter_mut().enumerate() {
+                ui.push_id(idx, |ui| {
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.strong(format!("{}. {}", idx + 1, op.name()));
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                if ui.small_button(ButtonKind::Delete.name()).clicked() {
+                                    removed = Some(idx);
+                                }
+                                if ui.small_button("\u{2B07}").clicked() {
+                                    moved = Some((idx, idx + 1));
+                                }
+                                if ui.small_button("\u{2B06}").clicked() && idx > 0 {
+                                    moved = Some((idx, idx - 1));
+                                }
+                            });
+                        });
+                        changed |= op.ui(ui);
+                    });
+                });
+            }
+        });
+    if let Some(idx) = removed {
+        ops.remove(idx);
+        changed = true;
+    } else if let Some((from, to)) = moved.filter(|(_, to)| *to < ops.len()) {
+        ops.swap(from, to);
+        changed = true;
+    }
+    changed
+}
+
+/// Live preview of an operation stack, rendered on the worker pool so
+/// dragging a slider never blocks the UI.
+#[derive(Default)]
+pub struct EditPreview {
+    dirty: bool,
+    /// dropping it cancels a render the stack changed since.
+    pending: Option<JobHandle<(Vec2, ColorImage)>>,
+    texture: Option<(MeteredTexture, Vec2)>,
+}
+
+impl EditPreview {
+    #[inline]
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Texture and size of the latest rendered preview, starting a new
+    /// render on `pool` when the stack changed since. The texture is rendered
+    /// from pixels downscaled to the texture size limit, the size is the one
+    /// of the baked result.
+    pub fn poll(
+        &mut self,
+        ctx: &Context,
+        pool: &WorkerPool,
+        meter: &TextureMeter,
+        image: &Arc<DynamicImage>,
+        ops: &[EditOp],
+        color: &ColorTransform,
+    ) -> Option<(TextureHandle, Vec2)> {
+        if self.dirty {
+            self.dirty = false;
+            let (image, ops, color) = (image.clone(), ops.to_vec(), color.clone());
+            let max_side = ctx.input(|i| i.max_texture_side);
+            self.pending = Some(pool.submit(ctx, 0, move |state| {
+                let (w, h) = ops
+                    .iter()
+                    .fold((image.width(), image.height()), |(w, h), op| {
+                        op.output_size(w, h)
+                    });
+                let scale = max_side as f32 / image.width().max(image.height()) as f32;
+                let edited = if scale < 1.0 {
+                    let source =
+                        image.resize(max_side as u32, max_side as u32, FilterType::Triangle);
+                    let ops = ops.iter().map(|op| op.scaled(scale)).collect::<Vec<_>>();
+                    (!state.is_cancelled()).then(|| apply_all(&ops, &source))?
+                } else {
+                    apply_all(&ops, &image)
+                };
+                let size = Vec2::new(w as f32, h as f32);
+                (!state.is_cancelled()).then(|| (size, color_image(&edited, max_side, &color)))
+            }));
+        }
+        if let Some(job) = &self.pending {
+            match job.try_recv() {
+                Ok((size, color_image)) => {
+                    let texture =
+                        ctx.load_texture("edit-preview", color_image, TextureOptions::NEAREST);
+                    self.texture = Some((meter.track(texture), size));
+                    self.pending = None;
+                }
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+        self.texture
+            .as_ref()
+            .map(|(texture, size)| (texture.handle().clone(), *size))
+    }
+}
@@ -0,0 +1,455 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+};
+
+use eframe::{
+    egui::{
+        collapsing_header::CollapsingState, panel::TopBottomSide, Button, CentralPanel, Context,
+        Image, Key, Label, Layout, Modifiers, ScrollArea, Sense, SidePanel, Spinner, TextEdit,
+        TextStyle, TopBottomPanel, Ui, Window,
+    },
+    emath::Align,
+    epaint::Vec2,
+};
+
+use crate::{
+    button::ButtonKind,
+    images::{filter_map_images_file, IVImages},
+    thumbnail::ThumbPoll,
+    thumbnail_cache::ThumbSize,
+};
+
+const MAX_RECENT_FOLDERS: usize = 10;
+const THUMBNAIL_SIZE: f32 = 48.0;
+const PREVIEW_SIZE: f32 = 192.0;
+
+#[derive(Debug, Clone)]
+struct Entry {
+    path: PathBuf,
+    name: String,
+    is_dir: bool,
+}
+
+/// In-app file browser rendered with egui only, so opening images does not
+/// depend on a desktop portal or native dialog being available.
+pub struct FileBrowser {
+    open: bool,
+    cwd: PathBuf,
+    cwd_edit: String,
+    entries: Vec<Entry>,
+    selected: Vec<PathBuf>,
+    anchor: Option<usize>,
+    recent: VecDeque<PathBuf>,
+    subdirs: HashMap<PathBuf, Vec<PathBuf>>,
+    show_hidden: bool,
+    show_thumbnails: bool,
+}
+
+impl FileBrowser {
+    pub fn new(start: PathBuf) -> Self {
+        Self {
+            open: false,
+            cwd_edit: start.display().to_string(),
+            cwd: start,
+            entries: vec![],
+            selected: vec![],
+            anchor: None,
+            recent: VecDeque::with_capacity(MAX_RECENT_FOLDERS),
+            subdirs: HashMap::new(),
+            show_hidden: false,
+            show_thumbnails: true,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.refresh();
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+    }
+
+    fn refresh(&mut self) {
+        self.subdirs.clear();
+        self.entries = read_entries(&self.cwd, self.show_hidden);
+        self.selected.clear();
+        self.anchor = None;
+    }
+
+    fn set_cwd(&mut self, dir: PathBuf) {
+        if !dir.is_dir() {
+            log::error!(
+                "Failed to open directory {} - (Reason: not a directory)",
+                dir.display()
+            );
+            self.cwd_edit = self.cwd.display().to_string();
+            return;
+        }
+        self.cwd_edit = dir.display().to_string();
+        self.cwd = dir;
+        self.entries = read_entries(&self.cwd, self.show_hidden);
+        self.selected.clear();
+        self.anchor = None;
+    }
+
+    fn push_recent(&mut self, dir: PathBuf) {
+        self.recent.retain(|p| p != &dir);
+        self.recent.push_front(dir);
+        self.recent.truncate(MAX_RECENT_FOLDERS);
+    }
+
+    fn cached_subdirs(&mut self, dir: &Path) -> Vec<PathBuf> {
+        let show_hidden = self.show_hidden;
+        self.subdirs
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| subdirs(dir, show_hidden))
+            .clone()
+    }
+
+    fn select(&mut self, idx: usize, modifiers: Modifiers) {
+        let path = self.entries[idx].path.clone();
+        if modifiers.shift {
+            let anchor = self.anchor.unwrap_or(idx);
+            let (lo, hi) = (anchor.min(idx), anchor.max(idx));
+            self.selected = self.entries[lo..=hi]
+                .iter()
+                .filter(|e| !e.is_dir)
+                .map(|e| e.path.clone())
+                .collect();
+            return;
+        }
+        if modifiers.command || modifiers.ctrl {
+            if let Some(pos) = self.selected.iter().position(|p| p == &path) {
+                self.selected.remove(pos);
+            } else {
+                self.selected.push(path);
+            }
+        } else {
+            self.selected = vec![path];
+        }
+        self.anchor = Some(idx);
+    }
+
+    /// Draw the browser window if it is open, returning the chosen image
+    /// paths once the user confirms the selection. Thumbnails are generated
+    /// by `images`, like the ones of the filmstrip.
+    pub fn show(&mut self, ctx: &Context, images: &mut IVImages) -> Option<Vec<PathBuf>> {
+        if !self.open {
+            return None;
+        }
+        let mut open = self.open;
+        let mut chosen = None;
+        let mut navigate = None;
+
+        Window::new(format!("{} Open", ButtonKind::Open))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_size(Vec2::new(720.0, 420.0))
+            .show(ctx, |ui| {
+                TopBottomPanel::new(TopBottomSide::Top, "fb_path").show_inside(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("\u{2B06}")
+                            .on_hover_text("Parent directory")
+                            .clicked()
+                        {
+                            navigate = self.cwd.parent().map(Path::to_path_buf);
+                        }
+                        if ui
+                            .button(ButtonKind::Refresh.name())
+                            .on_hover_text("Reload directory")
+                            .clicked()
+                        {
+                            self.refresh();
+                        }
+                        if ui.checkbox(&mut self.show_hidden, "Hidden").changed() {
+                            self.refresh();
+                        }
+                        ui.checkbox(&mut self.show_thumbnails, "Thumbnails");
+                        let edit = ui.add(
+                            TextEdit::singleline(&mut self.cwd_edit).desired_width(f32::INFINITY),
+                        );
+                        if edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                            navigate = Some(PathBuf::from(&self.cwd_edit));
+                        }
+                    });
+                });
+
+                TopBottomPanel::new(TopBottomSide::Bottom, "fb_actions").show_inside(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} selected", self.selected.len()));
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button("Cancel").clicked() {
+                                chosen = Some(vec![]);
+                            }
+                            if ui
+                                .button("Open folder")
+                                .on_hover_text("Open every image in this directory")
+                                .clicked()
+                            {
+                                chosen = Some(
+                                    self.entries
+                                        .iter()
+                                        .filter(|e| !e.is_dir)
+                                        .map(|e| e.path.clone())
+                                        .collect(),
+                                );
+                            }
+                            if ui
+                                .add_enabled(!self.selected.is_empty(), Button::new("Open"))
+                                .clicked()
+                            {
+                                chosen = Some(self.selected.clone());
+                            }
+                        });
+                    });
+                });
+
+                SidePanel::left("fb_tree")
+                    .resizable(true)
+                    .default_width(180.0)
+                    .show_inside(ui, |ui| {
+                        ScrollArea::vertical()
+                            .id_source("fb_tree_scroll")
+                            .show(ui, |ui| {
+                                ui.strong("Recent");
+                                for dir in self.recent.clone() {
+                                    let name = display_name(&dir);
+                                    if ui
+                                        .selectable_label(dir == self.cwd, name)
+                                        .on_hover_text(dir.display().to_string())
+                                        .clicked()
+                                    {
+                                        navigate = Some(dir);
+                                    }
+                                }
+                                ui.separator();
+                                ui.strong("Folders");
+                                for root in roots(&self.cwd) {
+                                    if let Some(dir) = self.tree_node(ui, &root) {
+                                        navigate = Some(dir);
+                                    }
+                                }
+                            });
+                    });
+
+                SidePanel::right("fb_preview")
+                    .resizable(false)
+                    .exact_width(PREVIEW_SIZE + 16.0)
+                    .show_inside(ui, |ui| {
+                        let Some(path) = self.selected.last() else {
+                            ui.weak("No image selected");
+                            return;
+                        };
+                        thumbnail_ui(ui, images, path, ThumbSize::Large, PREVIEW_SIZE, 0);
+                        ui.label(display_name(path));
+                        if let Ok(meta) = std::fs::metadata(path) {
+                            ui.weak(format!("{:.1} KiB", meta.len() as f64 / 1024.0));
+                        }
+                    });
+
+                CentralPanel::default().show_inside(ui, |ui| match self.entries_list(ui, images) {
+                    Some(entry) if entry.is_dir => navigate = Some(entry.path),
+                    Some(entry) => chosen = Some(vec![entry.path]),
+                    None => (),
+                });
+            });
+
+        if let Some(dir) = navigate {
+            self.set_cwd(dir);
+        }
+        if let Some(paths) = chosen.as_ref() {
+            if !paths.is_empty() {
+                self.push_recent(self.cwd.clone());
+            }
+            open = false;
+        }
+        if !open {
+            self.close();
+        }
+        chosen.filter(|paths| !paths.is_empty())
+    }
+
+    fn tree_node(&mut self, ui: &mut Ui, dir: &Path) -> Option<PathBuf> {
+        let mut navigate = None;
+        let id = ui.make_persistent_id(dir);
+        let is_cwd = dir == self.cwd;
+        let name = display_name(dir);
+        CollapsingState::load_with_default_open(ui.ctx(), id, self.cwd.starts_with(dir) && !is_cwd)
+            .show_header(ui, |ui| {
+                if ui.selectable_label(is_cwd, name).clicked() {
+                    navigate = Some(dir.to_path_buf());
+                }
+            })
+            .body(|ui| {
+                for child in self.cached_subdirs(dir) {
+                    if let Some(dir) = self.tree_node(ui, &child) {
+                        navigate = Some(dir);
+                    }
+                }
+            });
+        navigate
+    }
+
+    /// List the current directory, returning the entry that was double clicked.
+    fn entries_list(&mut self, ui: &mut Ui, images: &mut IVImages) -> Option<Entry> {
+        let mut activated = None;
+        let row_height = if self.show_thumbnails {
+            THUMBNAIL_SIZE
+        } else {
+            ui.text_style_height(&TextStyle::Body)
+        };
+        ScrollArea::vertical()
+            .id_source("fb_entries_scroll")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, self.entries.len(), |ui, range| {
+                let first = range.start;
+                for idx in range {
+                    let entry = self.entries[idx].clone();
+                    let selected = self.selected.contains(&entry.path);
+                    let res = ui
+                        .horizontal(|ui| {
+                            ui.set_height(row_height);
+                            if self.show_thumbnails && !entry.is_dir {
+                                thumbnail_ui(
+                                    ui,
+                                    images,
+                                    &entry.path,
+                                    ThumbSize::Normal,
+                                    THUMBNAIL_SIZE,
+                                    idx - first,
+                                );
+                            } else {
+                                let icon = if entry.is_dir {
+                                    "\u{1F5C0}"
+                                } else {
+                                    "\u{1F5BC}"
+                                };
+                                let width = if self.show_thumbnails {
+                                    THUMBNAIL_SIZE
+                                } else {
+                                    row_height
+                                };
+                                ui.add_sized(Vec2::new(width, row_height), Label::new(icon));
+                            }
+                            ui.selectable_label(selected, &entry.name)
+                        })
+                        .inner
+                        .interact(Sense::click());
+                    if res.double_clicked() {
+                        activated = Some(entry);
+                    } else if res.clicked() && !entry.is_dir {
+                        let modifiers = ui.input(|i| i.modifiers);
+                        self.select(idx, modifiers);
+                    }
+                }
+            });
+        activated
+    }
+}
+
+/// Thumbnail of `path` fitted in a `side` square, a spinner until it is
+/// generated and the image icon when it can not be.
+fn thumbnail_ui(
+    ui: &mut Ui,
+    images: &mut IVImages,
+    path: &Path,
+    size: ThumbSize,
+    side: f32,
+    priority: usize,
+) {
+    match images.file_thumbnail(ui.ctx(), path, size, priority) {
+        ThumbPoll::Ready(texture) => {
+            ui.add_sized(
+                Vec2::splat(side),
+                Image::new(&texture).max_size(Vec2::splat(side)),
+            );
+        }
+        ThumbPoll::Pending => {
+            ui.add_sized(Vec2::splat(side), Spinner::new());
+        }
+        ThumbPoll::Failed(err) => {
+            ui.add_sized(Vec2::splat(side), Label::new("\u{1F5BC}"))
+                .on_hover_text(err);
+        }
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn roots(cwd: &Path) -> Vec<PathBuf> {
+    let mut roots = vec![];
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        roots.push(home);
+    }
+    if let Some(root) = cwd.ancestors().last() {
+        roots.push(root.to_path_buf());
+    }
+    roots
+}
+
+fn subdirs(dir: &Path, show_hidden: bool) -> Vec<PathBuf> {
+    read_entries(dir, show_hidden)
+        .into_iter()
+        .filter(|e| e.is_dir)
+        .map(|e| e.path)
+        .collect()
+}
+
+/// Sorted, non hidden subdirectories of `dir`.
+#[inline]
+pub fn list_subdirs(dir: &Path) -> Vec<PathBuf> {
+    subdirs(dir, false)
+}
+
+/// Directories first, then every file `filter_map_images_file` accepts,
+/// both sorted by name.
+fn read_entries(dir: &Path, show_hidden: bool) -> Vec<Entry> {
+    let readdir = match std::fs::read_dir(dir) {
+        Ok(ok) => ok,
+        Err(err) => {
+            log::error!(
+                "Failed to read directory {} - (Reason: {err})",
+                dir.display()
+            );
+            return vec![];
+        }
+    };
+    let mut entries = readdir
+        .filter_map(|result_direntry| match result_direntry {
+            Ok(entry) => {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if !show_hidden && name.starts_with('.') {
+                    return None;
+                }
+                let path = entry.path();
+                if path.is_dir() {
+                    Some(Entry {
+                        path,
+                        name,
+                        is_dir: true,
+                    })
+                } else {
+                    filter_map_images_file(path).map(|(_, path)| Entry {
+                        path,
+                        name,
+                        is_dir: false,
+                    })
+                }
+            }
+            Err(err) => {
+                log::error!("Failed to readdir on path - (Reason: {err})");
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
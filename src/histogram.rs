@@ -0,0 +1,200 @@
+use eframe::{
+    egui::{Grid, Sense, Ui},
+    epaint::{Color32, ColorImage, Pos2, Rect, Rounding, Shape, Stroke, Vec2},
+};
+use image::DynamicImage;
+
+const BINS: usize = 256;
+/// Height of the histogram graph, in points.
+const GRAPH_HEIGHT: f32 = 120.0;
+/// Painted over pixels with a channel at its maximum.
+const CLIPPED_HIGHLIGHT: Color32 = Color32::from_rgba_premultiplied(200, 0, 0, 200);
+/// Painted over pixels with a channel at zero.
+const CLIPPED_SHADOW: Color32 = Color32::from_rgba_premultiplied(0, 0, 200, 200);
+
+/// Channels of a [`Histogram`], in the order of its bins.
+pub const CHANNELS: [(&str, Color32); 4] = [
+    ("R", Color32::from_rgb(230, 60, 60)),
+    ("G", Color32::from_rgb(60, 200, 60)),
+    ("B", Color32::from_rgb(70, 110, 240)),
+    ("Luma", Color32::GRAY),
+];
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChannelStats {
+    pub min: u8,
+    pub max: u8,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// pixels at zero.
+    pub clipped_shadows: u64,
+    /// pixels at 255.
+    pub clipped_highlights: u64,
+}
+
+/// 8 bit histograms of the red, green, blue and Rec. 709 luma channels.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    pub bins: [[u64; BINS]; 4],
+    pub stats: [ChannelStats; 4],
+    pub pixels: u64,
+}
+
+impl Histogram {
+    /// Histogram of `image`, or of the `region` of it in pixels.
+    pub fn compute(image: &DynamicImage, region: Option<Rect>) -> Self {
+        let image = match region {
+            Some(region) => image
+                .crop_imm(
+                    region.min.x as u32,
+                    region.min.y as u32,
+                    region.width() as u32,
+                    region.height() as u32,
+                )
+                .to_rgba8(),
+            None => image.to_rgba8(),
+        };
+        let mut bins = [[0u64; BINS]; 4];
+        for pixel in image.pixels() {
+            let [r, g, b, _] = pixel.0;
+            let luma = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+            for (channel, value) in [r, g, b, luma.round() as u8].into_iter().enumerate() {
+                bins[channel][value as usize] += 1;
+            }
+        }
+        let pixels = image.pixels().len() as u64;
+        Self {
+            stats: bins.map(|bins| stats(&bins, pixels)),
+            bins,
+            pixels,
+        }
+    }
+}
+
+fn stats(bins: &[u64; BINS], pixels: u64) -> ChannelStats {
+    if pixels == 0 {
+        return ChannelStats::default();
+    }
+    let used = || bins.iter().enumerate().filter(|(_, count)| **count > 0);
+    let (min, max) = (used().next(), used().next_back());
+    let n = pixels as f64;
+    let mean = used()
+        .map(|(value, count)| value as f64 * *count as f64)
+        .sum::<f64>()
+        / n;
+    let variance = used()
+        .map(|(value, count)| (value as f64 - mean).powi(2) * *count as f64)
+        .sum::<f64>()
+        / n;
+    ChannelStats {
+        min: min.map_or(0, |(value, _)| value as u8),
+        max: max.map_or(0, |(value, _)| value as u8),
+        mean,
+        std_dev: variance.sqrt(),
+        clipped_shadows: bins[0],
+        clipped_highlights: bins[BINS - 1],
+    }
+}
+
+/// Overlay of `image` marking pixels with any channel clipped, highlights in
+/// red over shadows in blue. Sampled down to fit `max_side`.
+pub fn clipping_overlay(image: &DynamicImage, max_side: usize) -> ColorImage {
+    let image = image.to_rgba8();
+    let (w, h) = (image.width() as usize, image.height() as usize);
+    let step = w.max(h).div_ceil(max_side.max(1)).max(1);
+    let size = [w.div_ceil(step), h.div_ceil(step)];
+    let mut overlay = ColorImage::new(size, Color32::TRANSPARENT);
+    for y in 0..size[1] {
+        for x in 0..size[0] {
+            let [r, g, b, _] = image.get_pixel((x * step) as u32, (y * step) as u32).0;
+            overlay.pixels[y * size[0] + x] = if r == u8::MAX || g == u8::MAX || b == u8::MAX {
+                CLIPPED_HIGHLIGHT
+            } else if r == 0 || g == 0 || b == 0 {
+                CLIPPED_SHADOW
+            } else {
+                Color32::TRANSPARENT
+            };
+        }
+    }
+    overlay
+}
+
+/// Graph of the channels, on a log scale when `log_scale`, and their
+/// statistics.
+pub fn histogram_ui(ui: &mut Ui, histogram: &Histogram, log_scale: bool) {
+    let size = Vec2::new(ui.available_width(), GRAPH_HEIGHT);
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, Rounding::ZERO, ui.visuals().extreme_bg_color);
+    let scale = |count: u64| {
+        if log_scale {
+            (count as f32).ln_1p()
+        } else {
+            count as f32
+        }
+    };
+    let top = histogram
+        .bins
+        .iter()
+        .flatten()
+        .map(|count| scale(*count))
+        .fold(0.0, f32::max)
+        .max(1.0);
+    let bin_width = rect.width() / BINS as f32;
+    // luma filled behind, the colour channels as lines over it.
+    let (_, luma_color) = CHANNELS[3];
+    for (value, count) in histogram.bins[3].iter().enumerate() {
+        let height = scale(*count) / top * rect.height();
+        let x = rect.left() + value as f32 * bin_width;
+        let bar = Rect::from_min_max(
+            Pos2::new(x, rect.bottom() - height),
+            Pos2::new(x + bin_width, rect.bottom()),
+        );
+        painter.rect_filled(bar, Rounding::ZERO, luma_color.gamma_multiply(0.5));
+    }
+    for (bins, (_, color)) in histogram.bins.iter().zip(CHANNELS).take(3) {
+        let points = bins
+            .iter()
+            .enumerate()
+            .map(|(value, count)| {
+                Pos2::new(
+                    rect.left() + (value as f32 + 0.5) * bin_width,
+                    rect.bottom() - scale(*count) / top * rect.height(),
+                )
+            })
+            .collect();
+        painter.add(Shape::line(points, Stroke::new(1.0, color)));
+    }
+
+    let percent = |count: u64| count as f64 * 100.0 / histogram.pixels.max(1) as f64;
+    Grid::new("iv_histogram_stats")
+        .num_columns(CHANNELS.len() + 1)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("");
+            for (name, color) in CHANNELS {
+                ui.colored_label(color, name);
+            }
+            ui.end_row();
+            let clipped = |count: u64| format!("{count} ({:.2}%)", percent(count));
+            let cells = histogram.stats.map(|s| {
+                [
+                    s.min.to_string(),
+                    s.max.to_string(),
+                    format!("{:.1}", s.mean),
+                    format!("{:.1}", s.std_dev),
+                    clipped(s.clipped_shadows),
+                    clipped(s.clipped_highlights),
+                ]
+            });
+            let rows = ["Min", "Max", "Mean", "Std dev", "Shadows", "Highlights"];
+            for (row, name) in rows.into_iter().enumerate() {
+                ui.strong(name);
+                for cells in &cells {
+                    ui.monospace(&cells[row]);
+                }
+                ui.end_row();
+            }
+        });
+    ui.weak(format!("{} pixels", histogram.pixels));
+}
@@ -0,0 +1,295 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    io::Write,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use image::{
+    codecs::png::{CompressionType, FilterType},
+    DynamicImage,
+};
+
+use crate::save::EncodeOptions;
+
+/// Memory the undo/redo stacks may hold before snapshots spill to disk.
+pub const HISTORY_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+pub const HISTORY_MAX_DEPTH: usize = 256;
+
+/// An undoable change, applied and reverted on `Target`.
+pub trait Command {
+    type Target;
+
+    /// Apply the change again, returning the index of the image it touched.
+    fn apply(&mut self, target: &mut Self::Target) -> Option<usize>;
+    /// Revert the change, returning the index of the image it touched.
+    fn revert(&mut self, target: &mut Self::Target) -> Option<usize>;
+
+    /// Snapshots this command keeps, in memory or spilled.
+    fn snapshots(&self) -> Vec<&Snapshot> {
+        vec![]
+    }
+    /// Move the snapshots this command keeps in memory into `dir`.
+    fn spill(&mut self, _dir: &mut SpillDir) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Undo and redo stacks with a bounded memory budget.
+pub struct History<C> {
+    undo: VecDeque<C>,
+    redo: Vec<C>,
+    budget: usize,
+    max_depth: usize,
+    spill_dir: SpillDir,
+}
+
+impl<C: Command> History<C> {
+    pub fn new(budget: usize, max_depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            budget,
+            max_depth,
+            spill_dir: SpillDir::new(),
+        }
+    }
+
+    /// Record a command that was already applied.
+    pub fn record(&mut self, cmd: C) {
+        self.redo.clear();
+        self.undo.push_back(cmd);
+        if self.undo.len() > self.max_depth {
+            self.undo.pop_front();
+        }
+        self.enforce_budget();
+    }
+
+    pub fn undo(&mut self, target: &mut C::Target) -> Option<usize> {
+        let mut cmd = self.undo.pop_back()?;
+        let idx = cmd.revert(target);
+        self.redo.push(cmd);
+        idx
+    }
+
+    pub fn redo(&mut self, target: &mut C::Target) -> Option<usize> {
+        let mut cmd = self.redo.pop()?;
+        let idx = cmd.apply(target);
+        self.undo.push_back(cmd);
+        idx
+    }
+
+    /// Directory snapshots kept outside the history are spilled into too.
+    #[inline]
+    pub fn spill_dir(&mut self) -> &mut SpillDir {
+        &mut self.spill_dir
+    }
+
+    /// Bytes of the snapshots kept in memory, a buffer shared by several
+    /// of them is counted once.
+    fn memory_used(&self) -> usize {
+        let mut seen = HashSet::new();
+        self.undo
+            .iter()
+            .chain(self.redo.iter())
+            .flat_map(C::snapshots)
+            .filter(|snapshot| snapshot.buffer().is_some_and(|ptr| seen.insert(ptr)))
+            .map(Snapshot::memory_size)
+            .sum()
+    }
+
+    /// Spill the oldest commands to disk until the in-memory snapshots fit
+    /// in the budget again.
+    fn enforce_budget(&mut self) {
+        let mut used = self.memory_used();
+        for idx in 0..self.undo.len() + self.redo.len() {
+            if used <= self.budget {
+                break;
+            }
+            let cmd = match self.undo.get_mut(idx) {
+                Some(cmd) => cmd,
+                None => &mut self.redo[idx - self.undo.len()],
+            };
+            if cmd
+                .snapshots()
+                .iter()
+                .all(|snapshot| snapshot.buffer().is_none())
+            {
+                continue;
+            }
+            match cmd.spill(&mut self.spill_dir) {
+                Ok(()) => used = self.memory_used(),
+                Err(err) => {
+                    log::error!("Failed to spill history snapshot - (Reason: {err})");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl<C> std::fmt::Debug for History<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("History")
+            .field("undo", &self.undo.len())
+            .field("redo", &self.redo.len())
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+/// Temporary directory spilled snapshots are written into, created on the
+/// first spill and removed on drop.
+pub struct SpillDir {
+    dir: Option<PathBuf>,
+    counter: usize,
+}
+
+impl SpillDir {
+    fn new() -> Self {
+        Self {
+            dir: None,
+            counter: 0,
+        }
+    }
+
+    fn next_path(&mut self, ext: &str) -> anyhow::Result<PathBuf> {
+        let dir = match &self.dir {
+            Some(dir) => dir,
+            None => self.dir.insert(create_private_temp_dir()?),
+        };
+        self.counter += 1;
+        Ok(dir.join(format!("{}.{ext}", self.counter)))
+    }
+}
+
+/// Fresh directory only the user can enter, under an unpredictable name so
+/// other users of a shared temporary directory can not plant it beforehand.
+fn create_private_temp_dir() -> std::io::Result<PathBuf> {
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    let mut attempts = 0;
+    loop {
+        let dir = std::env::temp_dir().join(format!(
+            "rziv-history-{}-{:016x}",
+            std::process::id(),
+            fastrand::u64(..)
+        ));
+        // not recursive, an existing directory is never reused.
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists && attempts < 8 => {
+                attempts += 1
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+impl Drop for SpillDir {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.dir {
+            std::fs::remove_dir_all(dir).unwrap_or_else(|err| {
+                log::error!(
+                    "Failed to remove history directory {} - (Reason: {err})",
+                    dir.display()
+                )
+            });
+        }
+    }
+}
+
+/// Pixels or encoded bytes kept by the history, either in memory or
+/// spilled to a file in [`SpillDir`].
+pub enum Snapshot {
+    Pixels(Arc<DynamicImage>),
+    Bytes(Arc<[u8]>),
+    SpilledPixels(PathBuf),
+    SpilledBytes(PathBuf),
+}
+
+impl Snapshot {
+    /// Address of the buffer kept in memory, shared by the clones of the
+    /// snapshotted `Arc`.
+    pub fn buffer(&self) -> Option<*const u8> {
+        match self {
+            Self::Pixels(image) => Some(Arc::as_ptr(image).cast()),
+            Self::Bytes(bytes) => Some(bytes.as_ptr()),
+            _ => None,
+        }
+    }
+
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Self::Pixels(image) => image.as_bytes().len(),
+            Self::Bytes(bytes) => bytes.len(),
+            _ => 0,
+        }
+    }
+
+    pub fn spill(&mut self, dir: &mut SpillDir) -> anyhow::Result<()> {
+        match self {
+            Self::Pixels(image) => {
+                // float pixels go to OpenEXR, everything else fits losslessly in PNG.
+                let (ext, options) =
+                    match image.color().bytes_per_pixel() / image.color().channel_count() {
+                        4 => ("exr", EncodeOptions::OpenExr),
+                        _ => (
+                            "png",
+                            EncodeOptions::Png {
+                                compression: CompressionType::Fast,
+                                filter: FilterType::Sub,
+                            },
+                        ),
+                    };
+                let path = dir.next_path(ext)?;
+                options.save(image, &path, None)?;
+                *self = Self::SpilledPixels(path);
+            }
+            Self::Bytes(bytes) => {
+                let path = dir.next_path("bin")?;
+                std::fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)?
+                    .write_all(bytes)?;
+                *self = Self::SpilledBytes(path);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    pub fn pixels(&self) -> anyhow::Result<Arc<DynamicImage>> {
+        match self {
+            Self::Pixels(image) => Ok(image.clone()),
+            Self::SpilledPixels(path) => Ok(Arc::new(image::open(path)?)),
+            _ => anyhow::bail!("Snapshot does not hold pixels"),
+        }
+    }
+
+    pub fn bytes(&self) -> anyhow::Result<Arc<[u8]>> {
+        match self {
+            Self::Bytes(bytes) => Ok(bytes.clone()),
+            Self::SpilledBytes(path) => Ok(std::fs::read(path)?.into()),
+            _ => anyhow::bail!("Snapshot does not hold bytes"),
+        }
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        if let Self::SpilledPixels(path) | Self::SpilledBytes(path) = self {
+            std::fs::remove_file(&*path).unwrap_or_else(|err| {
+                log::error!(
+                    "Failed to remove history snapshot {} - (Reason: {err})",
+                    path.display()
+                )
+            });
+        }
+    }
+}
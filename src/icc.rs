@@ -0,0 +1,393 @@
+use std::{
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use flate2::{write::ZlibEncoder, Compression};
+use image::{
+    codecs::{jpeg::JpegDecoder, png::PngDecoder, tiff::TiffDecoder},
+    ColorType, ImageDecoder, ImageFormat,
+};
+use qcms::{DataType, Intent, Profile, Transform};
+
+/// Data colour space of RGB and gray profiles, at offset 16 of the header.
+const RGB_SIGNATURE: &[u8] = b"RGB ";
+const GRAY_SIGNATURE: &[u8] = b"GRAY";
+const JPEG_ICC_MARKER: &[u8] = b"ICC_PROFILE\0";
+/// Profile bytes in one APP2 segment, what is left of the 16 bit segment
+/// length after the length itself, the marker and the sequence numbers.
+const JPEG_ICC_CHUNK: usize = u16::MAX as usize - 2 - JPEG_ICC_MARKER.len() - 2;
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// End of the IHDR chunk: signature, length, type, 13 bytes of data and crc.
+const PNG_IHDR_END: usize = 8 + 4 + 4 + 13 + 4;
+
+/// Where images are converted to before upload, from their embedded
+/// profile or sRGB when they have none.
+#[derive(Clone)]
+pub struct ColorManagement {
+    /// off shows the pixels as stored.
+    enabled: bool,
+    display: Arc<Profile>,
+    /// file the display profile was loaded from, `None` for sRGB.
+    display_path: Option<PathBuf>,
+    /// bumped on every change, textures made before are redone.
+    generation: u64,
+}
+
+impl Default for ColorManagement {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            display: srgb_output(),
+            display_path: None,
+            generation: 0,
+        }
+    }
+}
+
+impl ColorManagement {
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[inline]
+    pub fn display_path(&self) -> Option<&Path> {
+        self.display_path.as_deref()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if self.enabled != enabled {
+            self.enabled = enabled;
+            self.generation += 1;
+        }
+    }
+
+    /// Convert to the monitor profile in `path` instead of sRGB.
+    pub fn load_display_profile(&mut self, path: &Path) -> anyhow::Result<()> {
+        let bytes = std::fs::read(path).map_err(|err| {
+            anyhow::anyhow!(
+                "Failed to read ICC profile {} - (Reason: {err})",
+                path.display()
+            )
+        })?;
+        let mut profile = Profile::new_from_slice(&bytes, false)
+            .filter(|_| bytes.get(16..20) == Some(RGB_SIGNATURE))
+            .ok_or_else(|| anyhow::anyhow!("{} is not an RGB ICC profile", path.display()))?;
+        profile.precache_output_transform();
+        self.display = Arc::from(profile);
+        self.display_path = Some(path.to_path_buf());
+        self.generation += 1;
+        Ok(())
+    }
+
+    pub fn reset_display_profile(&mut self) {
+        if self.display_path.take().is_some() {
+            self.display = srgb_output();
+            self.generation += 1;
+        }
+    }
+
+    /// Transform for pixels tagged with `embedded`, untagged ones are taken
+    /// as sRGB.
+    pub fn transform(&self, embedded: Option<&[u8]>) -> ColorTransform {
+        let transform = if self.enabled {
+            self.build(embedded).map(Arc::new)
+        } else {
+            None
+        };
+        ColorTransform {
+            generation: Some(self.generation),
+            transform,
+        }
+    }
+
+    fn build(&self, embedded: Option<&[u8]>) -> Option<Transform> {
+        // gray and CMYK profiles do not describe the rgba8 texture pixels.
+        let input = embedded
+            .filter(|bytes| bytes.get(16..20) == Some(RGB_SIGNATURE))
+            .and_then(|bytes| {
+                let profile = Profile::new_from_slice(bytes, false);
+                if profile.is_none() {
+                    log::debug!("Unsupported embedded ICC profile, taken as sRGB");
+                }
+                profile
+            })
+            .unwrap_or_else(Profile::new_sRGB);
+        if input.is_sRGB() && self.display.is_sRGB() {
+            return None;
+        }
+        let transform = Transform::new(&input, &self.display, DataType::RGBA8, Intent::Perceptual);
+        if transform.is_none() {
+            log::debug!("Failed to create ICC transform, image is shown unmanaged");
+        }
+        transform
+    }
+}
+
+impl std::fmt::Debug for ColorManagement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ColorManagement")
+            .field("enabled", &self.enabled)
+            .field("display_path", &self.display_path)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+fn srgb_output() -> Arc<Profile> {
+    let mut profile = Profile::new_sRGB();
+    profile.precache_output_transform();
+    Arc::from(profile)
+}
+
+/// Conversion of texture pixels to the display, made with the
+/// [`ColorManagement`] of `generation`. The default one is stale and leaves
+/// the pixels alone.
+#[derive(Clone, Default)]
+pub struct ColorTransform {
+    generation: Option<u64>,
+    transform: Option<Arc<Transform>>,
+}
+
+impl ColorTransform {
+    /// Made with the current settings of `color`.
+    #[inline]
+    pub fn is_current(&self, color: &ColorManagement) -> bool {
+        self.generation == Some(color.generation)
+    }
+
+    /// Convert rgba8 `pixels` in place.
+    pub fn apply(&self, pixels: &mut [u8]) {
+        if let Some(transform) = &self.transform {
+            transform.apply(pixels);
+        }
+    }
+}
+
+/// ICC profile embedded in the encoded `bytes`. CMYK and other profiles
+/// are left out, the decoders convert those pixels to RGB.
+pub fn extract(bytes: &[u8], fmt: ImageFormat) -> Option<Vec<u8>> {
+    let profile = match fmt {
+        ImageFormat::Jpeg => JpegDecoder::new(Cursor::new(bytes)).ok()?.icc_profile(),
+        ImageFormat::Png => PngDecoder::new(Cursor::new(bytes)).ok()?.icc_profile(),
+        ImageFormat::Tiff => TiffDecoder::new(Cursor::new(bytes)).ok()?.icc_profile(),
+        // the image decoder decodes the whole image up front, the chunk is
+        // found without it.
+        ImageFormat::WebP => webp_profile(bytes),
+        _ => None,
+    };
+    profile.filter(|profile| matches!(profile.get(16..20), Some(RGB_SIGNATURE | GRAY_SIGNATURE)))
+}
+
+/// `profile` is of the colour space of pixels of type `color`, gray or RGB.
+pub fn describes(profile: &[u8], color: ColorType) -> bool {
+    let signature = if color.has_color() {
+        RGB_SIGNATURE
+    } else {
+        GRAY_SIGNATURE
+    };
+    profile.get(16..20) == Some(signature)
+}
+
+/// Formats [`embed`] can write a profile into.
+#[inline]
+pub fn can_embed(fmt: ImageFormat) -> bool {
+    matches!(
+        fmt,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP
+    )
+}
+
+/// Insert `profile` into `encoded`, as written by the encoders of `image`.
+pub fn embed(encoded: &[u8], fmt: ImageFormat, profile: &[u8]) -> anyhow::Result<Vec<u8>> {
+    match fmt {
+        ImageFormat::Jpeg => embed_jpeg(encoded, profile),
+        ImageFormat::Png => embed_png(encoded, profile),
+        ImageFormat::WebP => embed_webp(encoded, profile),
+        _ => anyhow::bail!("Embedding an ICC profile in {fmt:?} is not supported"),
+    }
+}
+
+/// APP2 segments right after the JFIF one, which has to come first.
+fn embed_jpeg(jpeg: &[u8], profile: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let [0xFF, 0xD8, rest @ ..] = jpeg else {
+        anyhow::bail!("Not a JPEG stream");
+    };
+    let jfif = match rest {
+        [0xFF, 0xE0, hi, lo, ..] => 2 + u16::from_be_bytes([*hi, *lo]) as usize,
+        _ => 0,
+    };
+    let at = 2 + jfif.min(rest.len());
+    let count = profile.len().div_ceil(JPEG_ICC_CHUNK);
+    if count > u8::MAX as usize {
+        anyhow::bail!("ICC profile is too large for JPEG");
+    }
+    let mut out = Vec::with_capacity(jpeg.len() + profile.len() + count * 18);
+    out.extend_from_slice(&jpeg[..at]);
+    for (idx, chunk) in profile.chunks(JPEG_ICC_CHUNK).enumerate() {
+        let len = 2 + JPEG_ICC_MARKER.len() + 2 + chunk.len();
+        out.extend_from_slice(&[0xFF, 0xE2]);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+        out.extend_from_slice(JPEG_ICC_MARKER);
+        out.extend_from_slice(&[idx as u8 + 1, count as u8]);
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&jpeg[at..]);
+    Ok(out)
+}
+
+/// iCCP chunk right after IHDR, before the palette and image data.
+fn embed_png(png: &[u8], profile: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if !png.starts_with(PNG_SIGNATURE) || png.get(12..16) != Some(b"IHDR") {
+        anyhow::bail!("Not a PNG stream");
+    }
+    // profile name, its terminator and the deflate compression method.
+    let mut zlib = ZlibEncoder::new(b"ICC profile\0\0".to_vec(), Compression::default());
+    zlib.write_all(profile)?;
+    let data = zlib.finish()?;
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(b"iCCP");
+    crc.update(&data);
+
+    let mut out = Vec::with_capacity(png.len() + data.len() + 12);
+    out.extend_from_slice(&png[..PNG_IHDR_END]);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(b"iCCP");
+    out.extend_from_slice(&data);
+    out.extend_from_slice(&crc.finalize().to_be_bytes());
+    out.extend_from_slice(&png[PNG_IHDR_END..]);
+    Ok(out)
+}
+
+/// Turn the simple lossless file into the extended format, which is a VP8X
+/// chunk with the ICC flag, then the ICCP chunk, then the image.
+fn embed_webp(webp: &[u8], profile: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if webp.get(..4) != Some(b"RIFF") || webp.get(8..12) != Some(b"WEBP") {
+        anyhow::bail!("Not a WebP stream");
+    }
+    let image = &webp[12..];
+    // VP8L header: signature, then 14 bits of width and height minus one
+    // and the alpha bit.
+    let [b'V', b'P', b'8', b'L', _, _, _, _, 0x2F, b0, b1, b2, b3, ..] = image else {
+        anyhow::bail!("Only lossless WebP can be extended with an ICC profile");
+    };
+    let bits = u32::from_le_bytes([*b0, *b1, *b2, *b3]);
+    let (width, height) = ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1);
+    let alpha = (bits >> 28) & 1 == 1;
+    let mut vp8x = [0u8; 10];
+    vp8x[0] = 0x20 | if alpha { 0x10 } else { 0 };
+    vp8x[4..7].copy_from_slice(&(width - 1).to_le_bytes()[..3]);
+    vp8x[7..10].copy_from_slice(&(height - 1).to_le_bytes()[..3]);
+
+    let mut body = b"WEBP".to_vec();
+    riff_chunk(&mut body, b"VP8X", &vp8x);
+    riff_chunk(&mut body, b"ICCP", profile);
+    body.extend_from_slice(image);
+    let mut out = Vec::with_capacity(body.len() + 8);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+fn riff_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(kind);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Data of the ICCP chunk, chunks are a fourcc, a little endian size and
+/// the data padded to an even size.
+fn webp_profile(bytes: &[u8]) -> Option<Vec<u8>> {
+    if bytes.get(..4) != Some(b"RIFF") || bytes.get(8..12) != Some(b"WEBP") {
+        return None;
+    }
+    let mut rest = &bytes[12..];
+    while rest.len() >= 8 {
+        let (header, data) = rest.split_at(8);
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let chunk = data.get(..size)?;
+        if &header[..4] == b"ICCP" {
+            return Some(chunk.to_vec());
+        }
+        rest = data.get((size + 1) & !1..)?;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageOutputFormat, RgbImage};
+
+    use super::*;
+
+    /// Not a usable profile, only its header signature is looked at. Odd
+    /// sized to check chunk padding.
+    fn profile(signature: &[u8], len: usize) -> Vec<u8> {
+        let mut profile = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+        profile[16..20].copy_from_slice(signature);
+        profile
+    }
+
+    fn encoded(fmt: ImageFormat) -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(3, 2));
+        let mut bytes = Cursor::new(vec![]);
+        let output = match fmt {
+            ImageFormat::Jpeg => ImageOutputFormat::Jpeg(90),
+            fmt => fmt.into(),
+        };
+        image.write_to(&mut bytes, output).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn embedded_profiles_are_extracted() {
+        let profile = profile(RGB_SIGNATURE, 301);
+        for fmt in [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP] {
+            assert!(can_embed(fmt));
+            let bytes = embed(&encoded(fmt), fmt, &profile).unwrap();
+            assert_eq!(extract(&bytes, fmt).as_ref(), Some(&profile), "{fmt:?}");
+            let image = image::load_from_memory_with_format(&bytes, fmt).unwrap();
+            assert_eq!((image.width(), image.height()), (3, 2), "{fmt:?}");
+        }
+        assert!(!can_embed(ImageFormat::Tiff));
+        assert!(embed(&encoded(ImageFormat::Tiff), ImageFormat::Tiff, &profile).is_err());
+    }
+
+    #[test]
+    fn large_profiles_span_jpeg_segments() {
+        let profile = profile(RGB_SIGNATURE, JPEG_ICC_CHUNK * 2 + 5);
+        let bytes = embed_jpeg(&encoded(ImageFormat::Jpeg), &profile).unwrap();
+        assert_eq!(extract(&bytes, ImageFormat::Jpeg), Some(profile));
+    }
+
+    #[test]
+    fn other_streams_are_refused() {
+        let png = encoded(ImageFormat::Png);
+        assert!(embed_jpeg(&png, &[]).is_err());
+        assert!(embed_png(&png[1..], &[]).is_err());
+        assert!(embed_webp(&png, &[]).is_err());
+        assert_eq!(webp_profile(&png), None);
+        assert_eq!(webp_profile(&encoded(ImageFormat::WebP)), None);
+    }
+
+    #[test]
+    fn only_gray_and_rgb_profiles_are_used() {
+        let cmyk = profile(b"CMYK", 128);
+        let bytes = embed_png(&encoded(ImageFormat::Png), &cmyk).unwrap();
+        assert_eq!(extract(&bytes, ImageFormat::Png), None);
+
+        let rgb = profile(RGB_SIGNATURE, 128);
+        let gray = profile(GRAY_SIGNATURE, 128);
+        assert!(describes(&rgb, ColorType::Rgba8));
+        assert!(!describes(&rgb, ColorType::L8));
+        assert!(describes(&gray, ColorType::La16));
+        assert!(!describes(&gray, ColorType::Rgb8));
+        assert!(!describes(&[], ColorType::Rgb8));
+    }
+}
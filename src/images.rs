@@ -1,43 +1,268 @@
 use std::{
     borrow::Cow,
+    collections::{BTreeSet, HashSet},
     fmt::Debug,
+    io::Cursor,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
+    sync::{mpsc::TryRecvError, Arc},
+    time::Duration,
 };
 
 use arboard::{Clipboard, ImageData};
 use eframe::{
     egui::{
-        self,
-        load::{Bytes, TexturePoll},
-        paint_texture_at, DroppedFile, Image, ImageSource, Key, Modifiers, Response, Sense,
-        Spinner, TextStyle, TextureOptions, Ui,
+        self, load::BytesPoll, CollapsingHeader, Context, DroppedFile, Grid, Key, Label, Modifiers,
+        Response, ScrollArea, Sense, Slider, Spinner, TextEdit, TextStyle, TextureHandle,
+        TextureOptions, Ui,
     },
-    emath::Align2,
+    emath::{Align, Align2},
     epaint::{Color32, ColorImage, Pos2, Rect, Rounding, Stroke, Vec2},
-    Frame,
 };
-use image::ImageFormat;
+use image::{DynamicImage, ImageFormat};
+
+use crate::{
+    animation::{self, Frames, Playback},
+    button::ButtonKind,
+    cache::{CacheKey, CachedImage, ImageCache, MeteredTexture, TextureMeter},
+    clipboard::PastedItem,
+    display::{self, DisplayOptions},
+    edit::{self, EditOp, EditPreview},
+    histogram::{self, Histogram},
+    history::{Command, History, Snapshot, SpillDir, HISTORY_MAX_DEPTH, HISTORY_MEMORY_BUDGET},
+    icc::{self, ColorManagement, ColorTransform},
+    inspector::{self, Pixels},
+    loader::{self, JobHandle, WorkerPool},
+    metadata::{self, Metadata},
+    orientation::Orientation,
+    save::EncodeOptions,
+    thumbnail::{ThumbPoll, ThumbSource, Thumbnails},
+    thumbnail_cache::ThumbSize,
+};
+
+/// Side of a thumbnail in the filmstrip, in points.
+const FILMSTRIP_SIDE: f32 = 64.0;
+const GALLERY_SIDES: RangeInclusive<f32> = 48.0..=256.0;
+/// Steps of the `+` and `-` keys, in percent of the actual pixels.
+const ZOOM_STEPS: &[f32] = &[
+    5.0, 10.0, 25.0, 33.3, 50.0, 66.7, 100.0, 150.0, 200.0, 300.0, 400.0, 600.0, 800.0, 1200.0,
+    1600.0, 3200.0,
+];
+/// Free zoom limits, in fractions of the actual pixels.
+const ZOOM_RANGE: RangeInclusive<f32> = 0.01..=64.0;
+
+/// How the shown image is scaled into the view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomMode {
+    Fit,
+    Fill,
+    /// one image pixel per screen pixel.
+    ActualPixels,
+    FitWidth,
+    FitHeight,
+    /// zoomed by the user, anchored at the cursor.
+    Free,
+}
+
+impl ZoomMode {
+    /// Selectable modes, their names and keys.
+    const ALL: [(Self, &'static str, Key); 5] = [
+        (Self::Fit, "Fit", Key::Num0),
+        (Self::ActualPixels, "1:1", Key::Num1),
+        (Self::Fill, "Fill", Key::Num2),
+        (Self::FitWidth, "Fit width", Key::Num3),
+        (Self::FitHeight, "Fit height", Key::Num4),
+    ];
+}
 
 #[derive(Clone)]
 enum ImgSourceType<'s> {
-    Buffer,
-    Uri,
+    /// encoded bytes of an image that has no file, e.g pasted from clipboard.
+    Buffer(Arc<[u8]>),
+    Uri(Cow<'s, str>),
     Path(Cow<'s, Path>),
 }
 
-#[derive(Clone)]
+type DecodeResult = Result<Decoded, String>;
+
+/// What a decoder job sends back to the UI thread.
+struct Decoded {
+    image: DynamicImage,
+    /// for the texture, downscaled to fit the texture size limit.
+    color_image: ColorImage,
+    /// all frames of animations.
+    frames: Option<Frames>,
+    orientation: Orientation,
+    icc_profile: Option<Arc<[u8]>>,
+    /// conversion `color_image` was made with.
+    color: ColorTransform,
+}
+
+/// Decoding state of an [`Img`], the pixels are decoded on a worker thread
+/// and uploaded as a texture once they arrive on the UI thread.
+enum ImgContent {
+    Empty,
+    Loading(JobHandle<DecodeResult>),
+    /// the texture is uploaded lazily when pixels were set from the UI thread.
+    Ready {
+        image: Arc<DynamicImage>,
+        texture: Option<TextureHandle>,
+        /// playback of animated images, `image` is their first frame.
+        animation: Option<Playback>,
+    },
+    Failed(String),
+}
+
+type MetadataResult = Result<Metadata, String>;
+
+/// Reading state of the metadata of the shown image.
+enum MetadataState {
+    Loading(JobHandle<MetadataResult>),
+    Done(MetadataResult),
+}
+
+impl Debug for MetadataState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Loading(_) => f.write_str("Loading"),
+            Self::Done(result) => f.debug_tuple("Done").field(&result.is_ok()).finish(),
+        }
+    }
+}
+
+/// What an analysis of the shown image was computed from.
+#[derive(Debug, Clone, PartialEq)]
+struct AnalysisKey {
+    source: CacheKey,
+    /// shown frame of animations.
+    frame: Option<usize>,
+    edits: Vec<EditOp>,
+    /// region of the pixels, the whole image when `None`.
+    region: Option<Rect>,
+}
+
+/// Result of a job on the shown pixels, redone once they change. The last
+/// result is kept meanwhile, unless another image is shown.
+struct Analysis<T> {
+    key: AnalysisKey,
+    job: Option<JobHandle<T>>,
+    result: Option<T>,
+    /// redo the work even though the pixels are the same.
+    stale: bool,
+}
+
+impl<T> Debug for Analysis<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Analysis")
+            .field("key", &self.key)
+            .field("pending", &self.job.is_some())
+            .field("ready", &self.result.is_some())
+            .finish()
+    }
+}
+
+impl<T> Analysis<T> {
+    /// Redo the work on the next update, e.g once its parameters changed.
+    fn invalidate(slot: &mut Option<Self>) {
+        if let Some(analysis) = slot {
+            analysis.stale = true;
+        }
+    }
+}
+
+impl<T: Send + 'static> Analysis<T> {
+    /// Start `work` on the pixels of `img`, edits baked in, when they changed
+    /// since `slot` was computed, and take the result once it arrives.
+    fn update(
+        slot: &mut Option<Self>,
+        ctx: &Context,
+        pool: &WorkerPool,
+        img: &mut Img,
+        region: Option<Rect>,
+        work: impl FnOnce(&DynamicImage) -> T + Send + 'static,
+    ) {
+        let Some(key) = img.analysis_key(region) else {
+            *slot = None;
+            return;
+        };
+        let current = slot
+            .as_ref()
+            .is_some_and(|analysis| analysis.key == key && !analysis.stale);
+        if !current {
+            let Some(pixels) = img.shown_pixels() else {
+                return;
+            };
+            let edits = key.edits.clone();
+            let job = pool.submit(ctx, 1, move |state| {
+                let baked;
+                let image = if edits.is_empty() {
+                    &*pixels
+                } else {
+                    baked = edit::apply_all(&edits, &pixels);
+                    &baked
+                };
+                (!state.is_cancelled()).then(|| work(image))
+            });
+            let result = slot
+                .take()
+                .filter(|analysis| analysis.key.source == key.source)
+                .and_then(|analysis| analysis.result);
+            *slot = Some(Self {
+                key,
+                job: Some(job),
+                result,
+                stale: false,
+            });
+        }
+        let Some(analysis) = slot else {
+            return;
+        };
+        if let Some(job) = &analysis.job {
+            match job.try_recv() {
+                Ok(result) => {
+                    analysis.result = Some(result);
+                    analysis.job = None;
+                }
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => analysis.job = None,
+            }
+        }
+    }
+}
+
 struct Img<'img> {
     fmt: ImageFormat,
     source: ImgSourceType<'img>,
-    content: Option<ImageSource<'img>>,
+    content: ImgContent,
+    edits: Vec<EditOp>,
+    preview: EditPreview,
+    /// cache identity of the decoded content, `None` when the pixels were
+    /// restored by the history and can not be reloaded from the source.
+    key: Option<CacheKey>,
+    /// identity of the source the thumbnail is generated from, computed once.
+    thumb_key: Option<CacheKey>,
+    /// view only rotation and flip by the user, kept for the session.
+    orientation: Orientation,
+    /// orientation asked for by the EXIF metadata of the source.
+    exif_orientation: Orientation,
+    /// ICC profile embedded in the source, the pixels are in its space.
+    icc_profile: Option<Arc<[u8]>>,
+    /// conversion of the textures to the display profile.
+    color: ColorTransform,
+    /// pixels the source can not give back, spilled to disk once unloaded
+    /// and read back from there.
+    snapshot: Option<Snapshot>,
 }
 impl Debug for Img<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut dbg = f.debug_struct("Img");
         dbg.field("fmt", &self.fmt);
+        dbg.field("edits", &self.edits);
         match &self.source {
-            ImgSourceType::Uri => dbg.field("source", &"Uri"),
-            ImgSourceType::Buffer => dbg.field("source", &"buffer"),
+            ImgSourceType::Uri(uri) => dbg.field("source", uri),
+            ImgSourceType::Buffer(bytes) => {
+                dbg.field("source", &format!("buffer({})", bytes.len()))
+            }
             ImgSourceType::Path(path) => dbg.field("source", path),
         };
         dbg.finish()
@@ -45,55 +270,871 @@ impl Debug for Img<'_> {
 }
 
 impl<'i> Img<'i> {
-    fn image(&mut self) -> Option<Image<'i>> {
-        if let Some(ref content) = self.content {
-            return Some(Image::new(content.clone()).texture_options(TextureOptions::NEAREST));
+    /// Start reading and decoding on `pool` if that did not happen yet, or
+    /// poll the running job, `priority` orders it against the other images.
+    fn load(
+        &mut self,
+        ctx: &Context,
+        pool: &WorkerPool,
+        cache: &mut ImageCache,
+        priority: usize,
+        color: &ColorManagement,
+    ) {
+        match &self.content {
+            ImgContent::Empty if self.snapshot.is_some() => {
+                self.start_restoring(ctx, pool, priority, color)
+            }
+            ImgContent::Empty => {
+                let key = self.source_key();
+                if let Some(cached) = cache.get(&key) {
+                    self.exif_orientation = cached.orientation;
+                    self.icc_profile = cached.icc_profile;
+                    self.color = cached.color;
+                    self.content = ImgContent::Ready {
+                        image: cached.image,
+                        texture: Some(cached.texture),
+                        animation: cached
+                            .frames
+                            .map(|frames| Playback::new(frames, cache.meter().clone())),
+                    };
+                } else {
+                    self.start_decoding(ctx, pool, priority, color);
+                }
+                self.key = Some(key);
+            }
+            ImgContent::Loading(job) => match job.try_recv() {
+                Ok(Ok(decoded)) => {
+                    let image = Arc::new(decoded.image);
+                    let frames = decoded.frames.map(Arc::new);
+                    let texture =
+                        ctx.load_texture(self.name(), decoded.color_image, TextureOptions::NEAREST);
+                    self.exif_orientation = decoded.orientation;
+                    self.icc_profile = decoded.icc_profile;
+                    self.color = decoded.color;
+                    if let Some(key) = &self.key {
+                        let cached = CachedImage {
+                            image: image.clone(),
+                            texture: texture.clone(),
+                            frames: frames.clone(),
+                            orientation: decoded.orientation,
+                            icc_profile: self.icc_profile.clone(),
+                            color: self.color.clone(),
+                        };
+                        cache.insert(key.clone(), cached);
+                    }
+                    self.content = ImgContent::Ready {
+                        image,
+                        texture: Some(texture),
+                        animation: frames
+                            .map(|frames| Playback::new(frames, cache.meter().clone())),
+                    };
+                }
+                Ok(Err(err)) => self.content = ImgContent::Failed(err),
+                Err(TryRecvError::Empty) => job.set_priority(priority),
+                Err(TryRecvError::Disconnected) => {
+                    self.content = ImgContent::Failed("decoder job is gone".to_owned())
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn source_key(&self) -> CacheKey {
+        match &self.source {
+            ImgSourceType::Buffer(bytes) => CacheKey::from_buffer(bytes),
+            ImgSourceType::Uri(uri) => CacheKey::Uri(uri.to_string()),
+            ImgSourceType::Path(path) => CacheKey::from_path(path),
+        }
+    }
+
+    /// Drop a read or decode still in flight, and the decoded content when
+    /// the cache evicted it, both start over once needed. Pixels the source
+    /// can not give back are spilled into `spill_dir` first.
+    fn unload(&mut self, cache: &ImageCache, spill_dir: &mut SpillDir) {
+        let evicted = match (&self.content, &self.key) {
+            (ImgContent::Loading(_), _) => true,
+            (ImgContent::Ready { .. }, Some(key)) => !cache.contains(key),
+            (ImgContent::Ready { .. }, None) => match &mut self.snapshot {
+                Some(snapshot) => match snapshot.spill(spill_dir) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        log::error!("Failed to spill image pixels - (Reason: {err})");
+                        false
+                    }
+                },
+                None => false,
+            },
+            _ => false,
+        };
+        if evicted {
+            self.content = ImgContent::Empty;
+            let edits = std::mem::take(&mut self.edits);
+            self.preview = EditPreview::default();
+            self.set_edits(edits);
+        }
+    }
+
+    /// Progress of a running job, `Some(None)` while it is still queued.
+    fn loading_progress(&self) -> Option<Option<f32>> {
+        match &self.content {
+            ImgContent::Loading(job) => Some(job.progress()),
+            _ => None,
+        }
+    }
+
+    /// Conversion to the display with the current `color` settings, the
+    /// textures made with older ones are redone.
+    fn color_transform(&mut self, color: &ColorManagement) -> &ColorTransform {
+        if let ImgContent::Ready {
+            texture, animation, ..
+        } = &mut self.content
+        {
+            if !self.color.is_current(color) {
+                self.color = color.transform(self.icc_profile.as_deref());
+                *texture = None;
+                if let Some(playback) = animation {
+                    playback.clear_textures();
+                }
+                if !self.edits.is_empty() {
+                    self.preview.invalidate();
+                }
+            }
+        }
+        &self.color
+    }
+
+    /// Texture and size of the decoded pixels, `None` until [`Img::load`]
+    /// got them.
+    fn texture(
+        &mut self,
+        ctx: &Context,
+        pool: &WorkerPool,
+        meter: &TextureMeter,
+        color: &ColorManagement,
+    ) -> Result<Option<(TextureHandle, Vec2)>, String> {
+        let name = self.name();
+        self.color_transform(color);
+        if let ImgContent::Ready {
+            image,
+            texture: texture @ None,
+            ..
+        } = &mut self.content
+        {
+            let max_side = ctx.input(|i| i.max_texture_side);
+            *texture = Some(ctx.load_texture(
+                name.clone(),
+                color_image(image, max_side, &self.color),
+                TextureOptions::NEAREST,
+            ));
+        }
+        match &mut self.content {
+            ImgContent::Ready {
+                image,
+                texture: Some(texture),
+                animation,
+            } => {
+                if !self.edits.is_empty() {
+                    if let Some(preview) =
+                        self.preview
+                            .poll(ctx, pool, meter, image, &self.edits, &self.color)
+                    {
+                        return Ok(Some(preview));
+                    }
+                } else if let Some(playback) = animation {
+                    playback.update(ctx);
+                    return Ok(Some(playback.texture(ctx, &name, &self.color)));
+                }
+                Ok(Some((
+                    texture.clone(),
+                    Vec2::new(image.width() as f32, image.height() as f32),
+                )))
+            }
+            ImgContent::Failed(err) => Err(err.clone()),
+            _ => Ok(None),
         }
+    }
+
+    fn start_decoding(
+        &mut self,
+        ctx: &Context,
+        pool: &WorkerPool,
+        priority: usize,
+        color: &ColorManagement,
+    ) {
+        let (fmt, color) = (self.fmt, color.clone());
+        let max_side = ctx.input(|i| i.max_texture_side);
+        let job = match &self.source {
+            ImgSourceType::Buffer(bytes) => {
+                let bytes = bytes.clone();
+                pool.submit(ctx, priority, move |state| {
+                    state.set_progress(1.0);
+                    Some(decode(&bytes, fmt, max_side, &color))
+                })
+            }
+            ImgSourceType::Path(path) => {
+                let path = path.to_path_buf();
+                pool.submit(ctx, priority, move |state| {
+                    let bytes = match loader::read_with_progress(&path, state) {
+                        Ok(bytes) => bytes?,
+                        Err(err) => {
+                            return Some(Err(format!(
+                                "Failed to read content of file: {} - (Reason: {err})",
+                                path.display()
+                            )))
+                        }
+                    };
+                    state.set_progress(1.0);
+                    (!state.is_cancelled()).then(|| decode(&bytes, fmt, max_side, &color))
+                })
+            }
+            ImgSourceType::Uri(uri) => match ctx.try_load_bytes(uri) {
+                Ok(BytesPoll::Ready { bytes, .. }) => {
+                    let bytes = Arc::<[u8]>::from(bytes.as_ref());
+                    pool.submit(ctx, priority, move |state| {
+                        state.set_progress(1.0);
+                        Some(decode(&bytes, fmt, max_side, &color))
+                    })
+                }
+                Ok(BytesPoll::Pending { .. }) => return,
+                Err(err) => {
+                    self.content =
+                        ImgContent::Failed(format!("Failed to load uri: {uri} - (Reason: {err})"));
+                    return;
+                }
+            },
+        };
+        self.content = ImgContent::Loading(job);
+    }
 
-        self.content = match &self.source {
-            ImgSourceType::Path(path) => match std::fs::read(path) {
-                Ok(ok) => Some(ImageSource::Bytes {
-                    uri: format!("bytes:/{}", path.display()).into(),
-                    bytes: ok.into(),
-                }),
+    /// Read back the pixels of the snapshot, on `pool` when they were spilled.
+    fn start_restoring(
+        &mut self,
+        ctx: &Context,
+        pool: &WorkerPool,
+        priority: usize,
+        color: &ColorManagement,
+    ) {
+        let path = match &self.snapshot {
+            Some(Snapshot::SpilledPixels(path)) => path.clone(),
+            Some(snapshot) => {
+                self.content = match snapshot.pixels() {
+                    Ok(image) => ImgContent::Ready {
+                        image,
+                        texture: None,
+                        animation: None,
+                    },
+                    Err(err) => {
+                        ImgContent::Failed(format!("Failed to restore image - (Reason: {err})"))
+                    }
+                };
+                return;
+            }
+            None => return,
+        };
+        let color = color.transform(self.icc_profile.as_deref());
+        let (orientation, icc_profile) = (self.exif_orientation, self.icc_profile.clone());
+        let max_side = ctx.input(|i| i.max_texture_side);
+        let job = pool.submit(ctx, priority, move |state| {
+            let image = match image::open(&path) {
+                Ok(image) => image,
                 Err(err) => {
-                    log::error!(
-                        "Failed to read content of file: {} - (Reason: {err})",
+                    return Some(Err(format!(
+                        "Failed to read spilled pixels: {} - (Reason: {err})",
                         path.display()
-                    );
-                    None
+                    )))
+                }
+            };
+            state.set_progress(1.0);
+            let color_image = color_image(&image, max_side, &color);
+            Some(Ok(Decoded {
+                image,
+                color_image,
+                frames: None,
+                orientation,
+                icc_profile,
+                color,
+            }))
+        });
+        self.content = ImgContent::Loading(job);
+    }
+
+    /// Read the metadata of the source on a worker, `None` while the bytes
+    /// of an uri are still being fetched.
+    fn read_metadata(&self, ctx: &Context, pool: &WorkerPool) -> Option<MetadataState> {
+        let fmt = self.fmt;
+        let job = match &self.source {
+            ImgSourceType::Buffer(bytes) => {
+                let bytes = bytes.clone();
+                pool.submit(ctx, 0, move |_| Some(Ok(metadata::read(&bytes, fmt))))
+            }
+            ImgSourceType::Path(path) => {
+                let path = path.to_path_buf();
+                pool.submit(ctx, 0, move |state| {
+                    let bytes = match loader::read_with_progress(&path, state) {
+                        Ok(bytes) => bytes?,
+                        Err(err) => {
+                            return Some(Err(format!(
+                                "Failed to read content of file: {} - (Reason: {err})",
+                                path.display()
+                            )))
+                        }
+                    };
+                    Some(Ok(metadata::read(&bytes, fmt)))
+                })
+            }
+            ImgSourceType::Uri(uri) => match ctx.try_load_bytes(uri) {
+                Ok(BytesPoll::Ready { bytes, .. }) => {
+                    let bytes = Arc::<[u8]>::from(bytes.as_ref());
+                    pool.submit(ctx, 0, move |_| Some(Ok(metadata::read(&bytes, fmt))))
+                }
+                Ok(BytesPoll::Pending { .. }) => return None,
+                Err(err) => {
+                    return Some(MetadataState::Done(Err(format!(
+                        "Failed to load uri: {uri} - (Reason: {err})"
+                    ))))
+                }
+            },
+        };
+        Some(MetadataState::Loading(job))
+    }
+
+    /// Playback of the animation, unless edits turned it into a still image.
+    fn animation(&mut self) -> Option<&mut Playback> {
+        match &mut self.content {
+            ImgContent::Ready {
+                animation: Some(playback),
+                ..
+            } if self.edits.is_empty() => Some(playback),
+            _ => None,
+        }
+    }
+
+    /// Decoded with more than one frame, edited or not.
+    #[inline]
+    fn is_animated(&self) -> bool {
+        matches!(
+            self.content,
+            ImgContent::Ready {
+                animation: Some(_),
+                ..
+            }
+        )
+    }
+
+    /// Decoded pixels, if they already arrived.
+    #[inline]
+    fn pixels(&self) -> Option<&Arc<DynamicImage>> {
+        match &self.content {
+            ImgContent::Ready { image, .. } => Some(image),
+            _ => None,
+        }
+    }
+
+    /// Pixels shown as they are stored, the current frame of animations.
+    /// Edits are not applied, they are baked on a worker for the inspector.
+    fn inspected_pixels(&self) -> Option<Pixels<'_>> {
+        match &self.content {
+            ImgContent::Ready {
+                animation: Some(playback),
+                ..
+            } => Some(Pixels::Frame(playback.current_frame())),
+            ImgContent::Ready { image, .. } => Some(Pixels::Image(image)),
+            _ => None,
+        }
+    }
+
+    /// Identity of what the image looks like, `None` until it is decoded.
+    fn analysis_key(&mut self, region: Option<Rect>) -> Option<AnalysisKey> {
+        let ImgContent::Ready { animation, .. } = &self.content else {
+            return None;
+        };
+        let frame = animation
+            .as_ref()
+            .filter(|_| self.edits.is_empty())
+            .map(Playback::current);
+        Some(AnalysisKey {
+            source: self.thumb_key(),
+            frame,
+            edits: self.edits.clone(),
+            region,
+        })
+    }
+
+    /// Decoded pixels, or a copy of the current frame of animations, before
+    /// edits.
+    fn shown_pixels(&self) -> Option<Arc<DynamicImage>> {
+        match &self.content {
+            ImgContent::Ready {
+                animation: Some(playback),
+                ..
+            } if self.edits.is_empty() => Some(Arc::new(DynamicImage::ImageRgba8(
+                playback.current_frame().clone(),
+            ))),
+            ImgContent::Ready { image, .. } => Some(image.clone()),
+            _ => None,
+        }
+    }
+
+    /// Decoded pixels with the edit stack baked in.
+    fn baked(&self) -> anyhow::Result<DynamicImage> {
+        let pixels = self
+            .pixels()
+            .ok_or_else(|| anyhow::anyhow!("Image is not loaded yet"))?;
+        Ok(edit::apply_all(&self.edits, pixels))
+    }
+
+    /// Decoded pixels with the edit stack baked in, turned upright as the
+    /// EXIF metadata asks when `apply_exif`.
+    fn baked_upright(&self, apply_exif: bool) -> anyhow::Result<DynamicImage> {
+        let baked = self.baked()?;
+        if apply_exif {
+            Ok(self.exif_orientation.apply_to_image(baked))
+        } else {
+            Ok(baked)
+        }
+    }
+
+    /// Orientation the image is shown with, the view turns of the user on
+    /// top of the EXIF one when `apply_exif`.
+    fn view_orientation(&self, apply_exif: bool) -> Orientation {
+        if apply_exif {
+            self.exif_orientation.then(self.orientation)
+        } else {
+            self.orientation
+        }
+    }
+
+    /// Replace the decoded pixels with those just saved to the source,
+    /// dropping the edit stack that produced them. They are taken as upright,
+    /// saving never writes EXIF metadata back.
+    fn set_pixels(&mut self, image: Arc<DynamicImage>) {
+        self.content = ImgContent::Ready {
+            image,
+            texture: None,
+            animation: None,
+        };
+        // not cached, so they are decoded from the saved file once unloaded.
+        self.key = Some(self.source_key());
+        self.snapshot = None;
+        self.thumb_key = None;
+        self.exif_orientation = Orientation::default();
+        self.set_edits(vec![]);
+    }
+
+    fn set_edits(&mut self, edits: Vec<EditOp>) {
+        self.edits = edits;
+        if self.edits.is_empty() {
+            self.preview = EditPreview::default();
+        } else {
+            self.preview.invalidate();
+        }
+    }
+
+    /// Detach from the list so the history can keep it, `with_pixels` also
+    /// snapshots the decoded pixels. Pixels the source can not give back are
+    /// always kept.
+    fn store(&self, with_pixels: bool) -> StoredImg<'i> {
+        let pixels = match (self.pixels(), &self.snapshot) {
+            _ if !with_pixels && self.snapshot.is_none() => None,
+            (Some(image), _) => Some(image.clone()),
+            (None, Some(snapshot)) => snapshot
+                .pixels()
+                .map_err(|err| log::error!("Failed to read spilled pixels - (Reason: {err})"))
+                .ok(),
+            (None, None) => None,
+        };
+        StoredImg {
+            fmt: self.fmt,
+            source: match &self.source {
+                ImgSourceType::Buffer(bytes) => {
+                    StoredSource::Buffer(Snapshot::Bytes(bytes.clone()))
+                }
+                ImgSourceType::Uri(uri) => StoredSource::Uri(uri.clone()),
+                ImgSourceType::Path(path) => StoredSource::Path(path.clone()),
+            },
+            pixels: pixels.map(Snapshot::Pixels),
+            edits: self.edits.clone(),
+            orientation: self.orientation,
+            exif_orientation: self.exif_orientation,
+            icc_profile: self.icc_profile.clone(),
+        }
+    }
+
+    fn name(&self) -> String {
+        match &self.source {
+            ImgSourceType::Buffer(bytes) => format!("buffer:{:p}", bytes.as_ptr()),
+            ImgSourceType::Uri(uri) => uri.to_string(),
+            ImgSourceType::Path(path) => path.display().to_string(),
+        }
+    }
+
+    fn thumb_key(&mut self) -> CacheKey {
+        if self.thumb_key.is_none() {
+            self.thumb_key = Some(self.source_key());
+        }
+        self.thumb_key.clone().expect("computed above")
+    }
+
+    /// What the thumbnail is generated from, the decoded pixels if those are
+    /// already there.
+    fn thumb_source(&self, ctx: &Context) -> Option<ThumbSource> {
+        if let Some(image) = self.pixels() {
+            return Some(ThumbSource::Pixels(image.clone(), self.exif_orientation));
+        }
+        match &self.source {
+            ImgSourceType::Buffer(bytes) => Some(ThumbSource::Bytes(bytes.clone())),
+            ImgSourceType::Path(path) => Some(ThumbSource::Path(path.to_path_buf())),
+            ImgSourceType::Uri(uri) => match ctx.try_load_bytes(uri) {
+                Ok(BytesPoll::Ready { bytes, .. }) => {
+                    Some(ThumbSource::Bytes(Arc::from(bytes.as_ref())))
                 }
+                _ => None,
             },
+        }
+    }
+}
+
+/// An [`Img`] kept by the history while it is out of the list or replaced.
+struct StoredImg<'img> {
+    fmt: ImageFormat,
+    source: StoredSource<'img>,
+    pixels: Option<Snapshot>,
+    edits: Vec<EditOp>,
+    orientation: Orientation,
+    exif_orientation: Orientation,
+    icc_profile: Option<Arc<[u8]>>,
+}
+
+enum StoredSource<'s> {
+    Buffer(Snapshot),
+    Uri(Cow<'s, str>),
+    Path(Cow<'s, Path>),
+}
+
+impl<'i> StoredImg<'i> {
+    /// Rebuild the [`Img`], a snapshot that can not be read back leaves it
+    /// failed rather than shifting the indices of the list.
+    fn restore(&self) -> Img<'i> {
+        let source = match &self.source {
+            StoredSource::Buffer(snapshot) => snapshot.bytes().map(ImgSourceType::Buffer),
+            StoredSource::Uri(uri) => Ok(ImgSourceType::Uri(uri.clone())),
+            StoredSource::Path(path) => Ok(ImgSourceType::Path(path.clone())),
+        };
+        let pixels = self.pixels.as_ref().map(Snapshot::pixels).transpose();
+        let snapshot = match &pixels {
+            Ok(Some(image)) => Some(Snapshot::Pixels(image.clone())),
+            _ => None,
+        };
+        let (source, content) = match (source, pixels) {
+            (Ok(source), Ok(Some(image))) => (
+                source,
+                ImgContent::Ready {
+                    image,
+                    texture: None,
+                    animation: None,
+                },
+            ),
+            (Ok(source), Ok(None)) => (source, ImgContent::Empty),
+            (Ok(source), Err(err)) => (
+                source,
+                ImgContent::Failed(format!("Failed to restore image - (Reason: {err})")),
+            ),
+            (Err(err), _) => (
+                ImgSourceType::Buffer(Arc::from([])),
+                ImgContent::Failed(format!("Failed to restore image - (Reason: {err})")),
+            ),
+        };
+        let mut img = Img {
+            fmt: self.fmt,
+            source,
+            content,
+            edits: vec![],
+            preview: EditPreview::default(),
+            key: None,
+            thumb_key: None,
+            orientation: self.orientation,
+            exif_orientation: self.exif_orientation,
+            icc_profile: self.icc_profile.clone(),
+            color: ColorTransform::default(),
+            snapshot,
+        };
+        img.set_edits(self.edits.clone());
+        img
+    }
+
+    fn snapshots(&self) -> impl Iterator<Item = &Snapshot> {
+        let source = match &self.source {
+            StoredSource::Buffer(snapshot) => Some(snapshot),
             _ => None,
         };
+        source.into_iter().chain(&self.pixels)
+    }
+
+    fn spill(&mut self, dir: &mut SpillDir) -> anyhow::Result<()> {
+        if let StoredSource::Buffer(snapshot) = &mut self.source {
+            snapshot.spill(dir)?;
+        }
+        if let Some(snapshot) = &mut self.pixels {
+            snapshot.spill(dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// Undoable change of the image list.
+enum Change<'img> {
+    /// edit stack of the image at `idx` replaced.
+    Edits {
+        idx: usize,
+        before: Vec<EditOp>,
+        after: Vec<EditOp>,
+    },
+    /// `count` images inserted at `idx`, kept in `stored` while undone.
+    Insert {
+        idx: usize,
+        count: usize,
+        stored: Vec<StoredImg<'img>>,
+    },
+    /// `count` images removed at `idx`, kept in `stored` while removed.
+    Remove {
+        idx: usize,
+        count: usize,
+        stored: Vec<StoredImg<'img>>,
+    },
+    /// image moved from `from` to `to`, e.g by dragging it in the filmstrip.
+    Move { from: usize, to: usize },
+    /// view rotation and flip of the image at `idx` changed.
+    Orient {
+        idx: usize,
+        before: Orientation,
+        after: Orientation,
+    },
+    /// edit stack baked into the pixels of the image at `idx` on save.
+    Bake {
+        idx: usize,
+        before: StoredImg<'img>,
+        after: StoredImg<'img>,
+    },
+}
+
+impl<'img> Change<'img> {
+    fn take(list: &mut Vec<Img<'img>>, idx: usize, count: usize) -> Vec<StoredImg<'img>> {
+        let end = (idx + count).min(list.len());
+        list.drain(idx.min(end)..end)
+            .map(|img| img.store(false))
+            .collect()
+    }
+
+    fn move_img(list: &mut Vec<Img<'img>>, from: usize, to: usize) {
+        if from < list.len() && to < list.len() {
+            let img = list.remove(from);
+            list.insert(to, img);
+        }
+    }
+
+    fn put(list: &mut Vec<Img<'img>>, idx: usize, stored: &mut Vec<StoredImg<'img>>) {
+        let idx = idx.min(list.len());
+        list.splice(idx..idx, stored.drain(..).map(|img| img.restore()));
+    }
+}
+
+impl<'img> Command for Change<'img> {
+    type Target = Vec<Img<'img>>;
+
+    fn apply(&mut self, target: &mut Self::Target) -> Option<usize> {
+        match self {
+            Self::Edits { idx, after, .. } => {
+                target.get_mut(*idx)?.set_edits(after.clone());
+                Some(*idx)
+            }
+            Self::Insert { idx, stored, .. } => {
+                Self::put(target, *idx, stored);
+                Some(*idx)
+            }
+            Self::Remove { idx, count, stored } => {
+                *stored = Self::take(target, *idx, *count);
+                Some(*idx)
+            }
+            Self::Move { from, to } => {
+                Self::move_img(target, *from, *to);
+                Some(*to)
+            }
+            Self::Orient { idx, after, .. } => {
+                target.get_mut(*idx)?.orientation = *after;
+                Some(*idx)
+            }
+            Self::Bake { idx, after, .. } => {
+                *target.get_mut(*idx)? = after.restore();
+                Some(*idx)
+            }
+        }
+    }
+
+    fn revert(&mut self, target: &mut Self::Target) -> Option<usize> {
+        match self {
+            Self::Edits { idx, before, .. } => {
+                target.get_mut(*idx)?.set_edits(before.clone());
+                Some(*idx)
+            }
+            Self::Insert { idx, count, stored } => {
+                *stored = Self::take(target, *idx, *count);
+                Some(idx.saturating_sub(1))
+            }
+            Self::Remove { idx, stored, .. } => {
+                Self::put(target, *idx, stored);
+                Some(*idx)
+            }
+            Self::Move { from, to } => {
+                Self::move_img(target, *to, *from);
+                Some(*from)
+            }
+            Self::Orient { idx, before, .. } => {
+                target.get_mut(*idx)?.orientation = *before;
+                Some(*idx)
+            }
+            Self::Bake { idx, before, .. } => {
+                *target.get_mut(*idx)? = before.restore();
+                Some(*idx)
+            }
+        }
+    }
+
+    fn snapshots(&self) -> Vec<&Snapshot> {
+        match self {
+            Self::Insert { stored, .. } | Self::Remove { stored, .. } => {
+                stored.iter().flat_map(StoredImg::snapshots).collect()
+            }
+            Self::Bake { before, after, .. } => {
+                before.snapshots().chain(after.snapshots()).collect()
+            }
+            Self::Edits { .. } | Self::Move { .. } | Self::Orient { .. } => vec![],
+        }
+    }
+
+    fn spill(&mut self, dir: &mut SpillDir) -> anyhow::Result<()> {
+        match self {
+            Self::Insert { stored, .. } | Self::Remove { stored, .. } => {
+                stored.iter_mut().try_for_each(|img| img.spill(dir))
+            }
+            Self::Bake { before, after, .. } => {
+                before.spill(dir)?;
+                after.spill(dir)
+            }
+            Self::Edits { .. } | Self::Move { .. } | Self::Orient { .. } => Ok(()),
+        }
+    }
+}
+
+/// Decode `bytes` into pixels, plus a color image for the texture that is
+/// downscaled when it does not fit in `max_side` and converted from the
+/// embedded profile as `color` asks, and every frame when the image is
+/// animated.
+fn decode(
+    bytes: &[u8],
+    fmt: ImageFormat,
+    max_side: usize,
+    color: &ColorManagement,
+) -> DecodeResult {
+    let reader = image::io::Reader::with_format(Cursor::new(bytes), fmt)
+        .with_guessed_format()
+        .map_err(|err| format!("Failed to guess image format - (Reason: {err})"))?;
+    let fmt = reader.format().unwrap_or(fmt);
+    let image = reader
+        .decode()
+        .map_err(|err| format!("Failed to decode image - (Reason: {err})"))?;
+    // a broken animation is still shown as its first frame.
+    let frames = animation::decode_frames(bytes, fmt).unwrap_or_else(|err| {
+        log::error!("{err}");
         None
+    });
+    let icc_profile = icc::extract(bytes, fmt).map(Arc::<[u8]>::from);
+    let color = color.transform(icc_profile.as_deref());
+    let color_image = color_image(&image, max_side, &color);
+    Ok(Decoded {
+        image,
+        color_image,
+        frames,
+        orientation: Orientation::from_exif(bytes),
+        icc_profile,
+        color,
+    })
+}
+
+/// Color image ready for texture upload, downscaled when it does not fit in
+/// `max_side` and converted by `color`. Float images are tone mapped.
+pub fn color_image(image: &DynamicImage, max_side: usize, color: &ColorTransform) -> ColorImage {
+    // linear float values need tone mapping to look right on screen.
+    if display::is_float(image) {
+        return DisplayOptions::default().transform(image, max_side, color);
     }
+    let max_side = max_side as u32;
+    let mut rgba = if image.width() > max_side || image.height() > max_side {
+        image
+            .resize(max_side, max_side, image::imageops::FilterType::Triangle)
+            .into_rgba8()
+    } else {
+        image.to_rgba8()
+    };
+    color.apply(&mut rgba);
+    let size = [rgba.width() as usize, rgba.height() as usize];
+    ColorImage::from_rgba_unmultiplied(size, rgba.as_raw())
 }
 
 impl<'i> Img<'i> {
-    #[allow(unused)]
+    #[inline]
+    fn path(&self) -> Option<&Path> {
+        match &self.source {
+            ImgSourceType::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
     fn from_uri(fmt: ImageFormat, uri: String) -> Self {
         Self {
             fmt,
-            source: ImgSourceType::Uri,
-            content: Some(ImageSource::Uri(uri.into())),
+            source: ImgSourceType::Uri(uri.into()),
+            content: ImgContent::Empty,
+            edits: vec![],
+            preview: EditPreview::default(),
+            key: None,
+            thumb_key: None,
+            orientation: Orientation::default(),
+            exif_orientation: Orientation::default(),
+            icc_profile: None,
+            color: ColorTransform::default(),
+            snapshot: None,
         }
     }
     fn from_path(fmt: ImageFormat, path: PathBuf) -> Self {
         Self {
             fmt,
             source: ImgSourceType::Path(path.into()),
-            content: None,
+            content: ImgContent::Empty,
+            edits: vec![],
+            preview: EditPreview::default(),
+            key: None,
+            thumb_key: None,
+            orientation: Orientation::default(),
+            exif_orientation: Orientation::default(),
+            icc_profile: None,
+            color: ColorTransform::default(),
+            snapshot: None,
         }
     }
-    fn from_bytes(fmt: ImageFormat, bytes: impl Into<Bytes>) -> Self {
+    fn from_bytes(fmt: ImageFormat, bytes: impl Into<Arc<[u8]>>) -> Self {
         Self {
             fmt,
-            source: ImgSourceType::Buffer,
-            content: Some(ImageSource::Bytes {
-                uri: "bytes://dynbytes.png".into(),
-                bytes: bytes.into(),
-            }),
+            source: ImgSourceType::Buffer(bytes.into()),
+            content: ImgContent::Empty,
+            edits: vec![],
+            preview: EditPreview::default(),
+            key: None,
+            thumb_key: None,
+            orientation: Orientation::default(),
+            exif_orientation: Orientation::default(),
+            icc_profile: None,
+            color: ColorTransform::default(),
+            snapshot: None,
         }
     }
 
@@ -110,26 +1151,177 @@ impl<'i> Img<'i> {
     }
 }
 
+/// Fade from the previously shown image, started once the new one is drawn.
+#[derive(Debug)]
+struct Crossfade {
+    from: usize,
+    started: Option<f64>,
+    duration: f64,
+}
+
 #[derive(Debug)]
 pub struct IVImages<'img> {
     images_sources: Vec<Img<'img>>,
+    history: History<Change<'img>>,
+    /// index and edit stack before an edit that is still being dragged.
+    pending_edits: Option<(usize, Vec<EditOp>)>,
     rect: Rect,
+    /// pixel size of the shown image, edits included.
+    image_size: Vec2,
+    /// view orientation of the shown image.
+    orientation: Orientation,
+    /// images are turned upright as their EXIF metadata asks.
+    apply_exif: bool,
+    /// index of the image and the selected region in its pixels.
+    selection: Option<(usize, Rect)>,
+    selection_anchor: Option<Pos2>,
     size: Option<Vec2>,
-    zoom: Vec2,
+    /// area the shown image is drawn in.
+    view: Rect,
+    zoom_mode: ZoomMode,
+    /// points per image pixel in [`ZoomMode::Free`].
+    zoom: f32,
+    pixels_per_point: f32,
+    /// offset of the image centre from the view centre.
     drag: Vec2,
     showed_idx: usize,
+    pool: WorkerPool,
+    cache: ImageCache,
+    thumbnails: Thumbnails,
+    /// image being dragged in the filmstrip.
+    filmstrip_dragged: Option<usize>,
+    /// shown image the filmstrip last scrolled to.
+    filmstrip_shown: Option<usize>,
+    gallery_selected: BTreeSet<usize>,
+    gallery_anchor: Option<usize>,
+    /// shown image the gallery last scrolled to.
+    gallery_shown: Option<usize>,
+    gallery_side: f32,
+    /// export of the shown animation frame asked for, see
+    /// [`IVImages::take_frame_export`].
+    frame_export: bool,
+    /// image the slideshow shows next, decoded ahead like the neighbours.
+    upcoming: Option<usize>,
+    crossfade: Option<Crossfade>,
+    /// previous and next arrows are not shown on hover.
+    hide_arrows: bool,
+    histogram: Option<Analysis<Histogram>>,
+    histogram_log: bool,
+    /// clipped highlights and shadows are marked over the shown image.
+    show_clipping: bool,
+    clipping: Option<Analysis<MeteredTexture>>,
+    /// channel, alpha and background modes of the shown image.
+    display: DisplayOptions,
+    /// texture of the shown image with the modes of `display_view_options`.
+    display_view: Option<Analysis<MeteredTexture>>,
+    display_view_options: DisplayOptions,
+    /// conversion of the images from their ICC profile to the display.
+    color: ColorManagement,
+    /// monitor profile path typed in the context menu.
+    display_profile_edit: String,
+    display_profile_error: Option<String>,
+    /// the pixel under the cursor is shown magnified with its values.
+    inspector: bool,
+    /// pixels of the shown image with its edits baked in, for the inspector.
+    inspected: Option<Analysis<Arc<DynamicImage>>>,
+    /// metadata of the shown image, read once the metadata panel is shown.
+    metadata: Option<(CacheKey, MetadataState)>,
+    /// the metadata panel shows the sections as read instead of fields.
+    metadata_raw: bool,
+    /// images on each side of the shown one decoded ahead of time.
+    prefetch: usize,
 }
 
 impl<'img> IVImages<'img> {
-    pub fn new(paths: Vec<PathBuf>) -> Self {
+    pub fn new(paths: Vec<PathBuf>, prefetch: usize, cache_budget: usize) -> Self {
         let images_sources = Img::from_paths(paths);
         Self {
             images_sources,
+            history: History::new(HISTORY_MEMORY_BUDGET, HISTORY_MAX_DEPTH),
+            pending_edits: None,
             size: None,
             rect: Rect::ZERO,
-            zoom: Vec2::splat(1f32),
+            image_size: Vec2::ZERO,
+            orientation: Orientation::default(),
+            apply_exif: true,
+            selection: None,
+            selection_anchor: None,
+            view: Rect::ZERO,
+            zoom_mode: ZoomMode::Fit,
+            zoom: 1.0,
+            pixels_per_point: 1.0,
             drag: Vec2::ZERO,
             showed_idx: 0,
+            pool: WorkerPool::new("decode"),
+            cache: ImageCache::new(cache_budget),
+            thumbnails: Thumbnails::default(),
+            filmstrip_dragged: None,
+            filmstrip_shown: None,
+            gallery_selected: BTreeSet::new(),
+            gallery_anchor: None,
+            gallery_shown: None,
+            gallery_side: 128.0,
+            frame_export: false,
+            upcoming: None,
+            crossfade: None,
+            hide_arrows: false,
+            histogram: None,
+            histogram_log: false,
+            show_clipping: false,
+            clipping: None,
+            display: DisplayOptions::default(),
+            display_view: None,
+            display_view_options: DisplayOptions::default(),
+            color: ColorManagement::default(),
+            display_profile_edit: String::new(),
+            display_profile_error: None,
+            inspector: false,
+            inspected: None,
+            metadata: None,
+            metadata_raw: false,
+            prefetch,
+        }
+    }
+
+    /// Load the shown image and its neighbours, cancelling jobs of the images
+    /// that went out of that window and unloading those the cache evicted.
+    fn prefetch(&mut self, ctx: &Context) {
+        let lo = self.showed_idx.saturating_sub(self.prefetch);
+        let hi = self.showed_idx.saturating_add(self.prefetch);
+        let (upcoming, fading) = (self.upcoming, self.crossfade.as_ref().map(|f| f.from));
+        let wanted =
+            |idx: usize| (lo..=hi).contains(&idx) || upcoming == Some(idx) || fading == Some(idx);
+        for (idx, img) in self.images_sources.iter_mut().enumerate() {
+            if wanted(idx) {
+                let priority = idx.abs_diff(self.showed_idx).min(self.prefetch + 1);
+                img.load(ctx, &self.pool, &mut self.cache, priority, &self.color);
+            }
+        }
+        let keep = self
+            .images_sources
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| wanted(*idx))
+            .filter_map(|(_, img)| img.key.as_ref())
+            .collect::<HashSet<_>>();
+        self.cache.evict(&keep);
+        for (idx, img) in self.images_sources.iter_mut().enumerate() {
+            if !wanted(idx) {
+                img.unload(&self.cache, self.history.spill_dir());
+            }
+        }
+    }
+
+    /// Record the images appended since the list had `old_len` entries.
+    fn record_insert(&mut self, old_len: usize) {
+        let count = self.images_sources.len().saturating_sub(old_len);
+        if count > 0 {
+            self.commit_pending_edits();
+            self.history.record(Change::Insert {
+                idx: old_len,
+                count,
+                stored: vec![],
+            });
         }
     }
 
@@ -137,6 +1329,7 @@ impl<'img> IVImages<'img> {
     where
         I: IntoIterator<Item = DroppedFile>,
     {
+        let old_len = self.images_sources.len();
         self.images_sources.extend(
             paths
                 .into_iter()
@@ -146,26 +1339,432 @@ impl<'img> IVImages<'img> {
                         .map(|(fmt, path)| Img::from_path(fmt, path))
                 })
                 .collect::<Vec<_>>(),
-        )
+        );
+        self.record_insert(old_len);
     }
-    pub fn extend_from_image_data(&mut self, img_data: ImageData<'_>) {
-        use std::io::Cursor;
-        let w = img_data.width as u32;
-        let h = img_data.height as u32;
-        let mut buffer = Cursor::new(Vec::with_capacity((w * h) as usize));
-        if let Err(err) = image::write_buffer_with_format(
-            &mut buffer,
-            &img_data.bytes,
-            w,
-            h,
-            image::ColorType::Rgba8,
-            image::ImageOutputFormat::Png,
-        ) {
-            log::error!("Failed to convert image from raw rgba to png - (Reason: {err})");
-            return;
-        }
-        self.images_sources
-            .push(Img::from_bytes(ImageFormat::Png, buffer.into_inner()))
+    /// Index of the image at `path`, appending it when it is not listed yet.
+    fn push_path(&mut self, path: PathBuf) -> Option<usize> {
+        let (fmt, path) = filter_map_images_file(path)?;
+        let listed = self
+            .images_sources
+            .iter()
+            .position(|img| img.path() == Some(path.as_path()));
+        Some(listed.unwrap_or_else(|| {
+            self.images_sources.push(Img::from_path(fmt, path));
+            self.images_sources.len() - 1
+        }))
+    }
+
+    /// Append the image files among `paths`, skipping those already listed,
+    /// and show the first of them.
+    pub fn extend_from_paths<I>(&mut self, paths: I)
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        self.extend_from_pasted(paths.into_iter().map(PastedItem::Path));
+    }
+
+    /// Append the images referenced by pasted text and show the first of them.
+    pub fn extend_from_pasted<I>(&mut self, items: I)
+    where
+        I: IntoIterator<Item = PastedItem>,
+    {
+        let old_len = self.images_sources.len();
+        let mut first = None;
+        for item in items {
+            let idx = match item {
+                PastedItem::Path(path) => self.push_path(path),
+                PastedItem::Uri(fmt, uri) => {
+                    self.images_sources.push(Img::from_uri(fmt, uri));
+                    Some(self.images_sources.len() - 1)
+                }
+                PastedItem::Bytes(fmt, bytes) => {
+                    self.images_sources.push(Img::from_bytes(fmt, bytes));
+                    Some(self.images_sources.len() - 1)
+                }
+            };
+            if first.is_none() {
+                first = idx;
+            }
+        }
+        self.record_insert(old_len);
+        if let Some(idx) = first {
+            self.showed_idx = idx;
+            log::debug!("setting opened index on: {}", self.showed_idx);
+        }
+    }
+    /// Remove the shown image from the list, the file itself is left alone.
+    pub fn remove_shown(&mut self) {
+        if self.showed_idx >= self.images_sources.len() {
+            return;
+        }
+        self.commit_pending_edits();
+        let mut change = Change::Remove {
+            idx: self.showed_idx,
+            count: 1,
+            stored: vec![],
+        };
+        if let Some(idx) = change.apply(&mut self.images_sources) {
+            self.show_idx(idx);
+        }
+        self.history.record(change);
+    }
+
+    /// Move the image at `from` to `to`, keeping it shown.
+    pub fn move_image(&mut self, from: usize, to: usize) {
+        let len = self.images_sources.len();
+        if from == to || from >= len || to >= len {
+            return;
+        }
+        self.commit_pending_edits();
+        let mut change = Change::Move { from, to };
+        if let Some(idx) = change.apply(&mut self.images_sources) {
+            self.show_idx(idx);
+        }
+        self.history.record(change);
+    }
+
+    pub fn undo(&mut self) {
+        self.commit_pending_edits();
+        if let Some(idx) = self.history.undo(&mut self.images_sources) {
+            self.show_idx(idx);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        self.commit_pending_edits();
+        if let Some(idx) = self.history.redo(&mut self.images_sources) {
+            self.show_idx(idx);
+        }
+    }
+
+    /// Record the edit made since `pending_edits` was taken, once it is done.
+    fn commit_pending_edits(&mut self) {
+        let Some((idx, before)) = self.pending_edits.take() else {
+            return;
+        };
+        let Some(img) = self.images_sources.get(idx) else {
+            return;
+        };
+        if img.edits != before {
+            let after = img.edits.clone();
+            self.history.record(Change::Edits { idx, before, after });
+        }
+    }
+
+    /// Index of the shown image and the number of images.
+    #[inline]
+    pub fn position(&self) -> (usize, usize) {
+        (self.showed_idx, self.images_sources.len())
+    }
+
+    /// The shown image is decoded, or failed to.
+    pub fn is_shown_ready(&self) -> bool {
+        self.images_sources.get(self.showed_idx).is_some_and(|img| {
+            matches!(
+                img.content,
+                ImgContent::Ready { .. } | ImgContent::Failed(_)
+            )
+        })
+    }
+
+    #[inline]
+    pub fn set_hide_arrows(&mut self, hide: bool) {
+        self.hide_arrows = hide;
+    }
+
+    #[inline]
+    pub fn set_upcoming(&mut self, idx: Option<usize>) {
+        self.upcoming = idx;
+    }
+
+    /// Show the image at `idx`, fading from the shown one over `duration`.
+    pub fn show_with_crossfade(&mut self, idx: usize, duration: Duration) {
+        self.crossfade = (!duration.is_zero()).then_some(Crossfade {
+            from: self.showed_idx,
+            started: None,
+            duration: duration.as_secs_f64(),
+        });
+        self.show_idx(idx);
+    }
+
+    #[inline]
+    fn show_idx(&mut self, idx: usize) {
+        self.showed_idx = idx.min(self.images_sources.len().saturating_sub(1));
+        log::debug!("setting shown index on: {}", self.showed_idx);
+    }
+
+    /// Path and format of the shown image, used to prefill the Save As dialog.
+    pub fn shown_path_and_format(&self) -> Option<(Option<&Path>, ImageFormat)> {
+        let img = self.images_sources.get(self.showed_idx)?;
+        Some((img.path(), img.fmt))
+    }
+
+    /// Re-encode the shown image back to its own path with its own format,
+    /// unless it has no edits and the file is left untouched. Returns `Ok(false)` when that is not possible (pasted image, format
+    /// without an encoder, animation, or an ICC profile the format can not be
+    /// written with) and Save As must be used instead.
+    pub fn save(&mut self) -> anyhow::Result<bool> {
+        self.commit_pending_edits();
+        let idx = self.showed_idx;
+        let Some(img) = self.images_sources.get_mut(idx) else {
+            return Ok(true);
+        };
+        let Some(path) = img.path() else {
+            return Ok(false);
+        };
+        let Some(options) = EncodeOptions::default_for_file(img.fmt, path) else {
+            return Ok(false);
+        };
+        // re-encoding would only lose quality and the metadata of the file.
+        if img.edits.is_empty() {
+            log::debug!("nothing to save to: {}", path.display());
+            return Ok(true);
+        }
+        // overwriting would silently drop the other frames, or the profile
+        // e.g of a TIFF.
+        if img.is_animated() || img.icc_profile.is_some() && !icc::can_embed(options.format()) {
+            return Ok(false);
+        }
+        let baked = img.baked_upright(self.apply_exif)?;
+        let embedded = options.save(&baked, path, img.icc_profile.as_deref())?;
+        log::debug!("saved image to: {}", path.display());
+        let before = img.store(true);
+        img.set_pixels(Arc::new(baked));
+        if !embedded {
+            img.icc_profile = None;
+            img.color = ColorTransform::default();
+        }
+        let after = img.store(true);
+        self.history.record(Change::Bake { idx, before, after });
+        Ok(true)
+    }
+
+    /// Encode the shown image to `path`, which then becomes its source. Its
+    /// ICC profile is dropped unless `embed_icc`.
+    pub fn save_as(
+        &mut self,
+        path: PathBuf,
+        options: EncodeOptions,
+        embed_icc: bool,
+    ) -> anyhow::Result<()> {
+        self.commit_pending_edits();
+        let idx = self.showed_idx;
+        let Some(img) = self.images_sources.get_mut(idx) else {
+            return Ok(());
+        };
+        let baked = img.baked_upright(self.apply_exif)?;
+        let icc_profile = img
+            .icc_profile
+            .clone()
+            .filter(|_| embed_icc && icc::can_embed(options.format()));
+        let embedded = options.save(&baked, &path, icc_profile.as_deref())?;
+        log::debug!("saved image as: {}", path.display());
+        let before = img.store(true);
+        img.fmt = options.format();
+        img.source = ImgSourceType::Path(path.into());
+        img.set_pixels(Arc::new(baked));
+        if !embedded {
+            img.icc_profile = None;
+            img.color = ColorTransform::default();
+        }
+        let after = img.store(true);
+        self.history.record(Change::Bake { idx, before, after });
+        Ok(())
+    }
+
+    /// Path suggested for the animation frame the user asked to export, once
+    /// per request.
+    pub fn take_frame_export(&mut self) -> Option<PathBuf> {
+        if !std::mem::take(&mut self.frame_export) {
+            return None;
+        }
+        let img = self.images_sources.get_mut(self.showed_idx)?;
+        let frame = img.animation()?.current() + 1;
+        let stem = img
+            .path()
+            .and_then(Path::file_stem)
+            .map_or_else(|| "image".into(), |s| s.to_string_lossy());
+        let name = format!("{stem}-frame-{frame}.png");
+        Some(match img.path().and_then(Path::parent) {
+            Some(dir) => dir.join(name),
+            None => PathBuf::from(name),
+        })
+    }
+
+    /// Encode the current frame of the shown animation to `path`, with the
+    /// ICC profile of the image when `embed_icc`.
+    pub fn export_frame(
+        &mut self,
+        path: PathBuf,
+        options: EncodeOptions,
+        embed_icc: bool,
+    ) -> anyhow::Result<()> {
+        let img = self
+            .images_sources
+            .get_mut(self.showed_idx)
+            .ok_or_else(|| anyhow::anyhow!("Image is not animated"))?;
+        let icc_profile = img
+            .icc_profile
+            .clone()
+            .filter(|_| embed_icc && icc::can_embed(options.format()));
+        let playback = img
+            .animation()
+            .ok_or_else(|| anyhow::anyhow!("Image is not animated"))?;
+        let frame = DynamicImage::ImageRgba8(playback.current_frame().clone());
+        options.save(&frame, &path, icc_profile.as_deref())?;
+        log::debug!("exported frame to: {}", path.display());
+        Ok(())
+    }
+
+    /// Edit panel content for the shown image.
+    pub fn edit_ui(&mut self, ui: &mut Ui) {
+        let Some(img) = self.images_sources.get_mut(self.showed_idx) else {
+            ui.weak("No image opened");
+            return;
+        };
+        if img.pixels().is_none() {
+            ui.weak("Image is not loaded yet");
+            return;
+        }
+        let before = img.edits.clone();
+        if edit::edit_stack_ui(ui, &mut img.edits) {
+            let edits = std::mem::take(&mut img.edits);
+            img.set_edits(edits);
+            self.pending_edits.get_or_insert((self.showed_idx, before));
+        }
+        // a slider drag is recorded as one change once it is released.
+        if !ui.input(|i| i.pointer.any_down()) {
+            self.commit_pending_edits();
+        }
+    }
+
+    /// Metadata panel content for the shown image, grouped by namespace or
+    /// as read.
+    pub fn metadata_ui(&mut self, ui: &mut Ui) {
+        let Some(img) = self.images_sources.get_mut(self.showed_idx) else {
+            ui.weak("No image opened");
+            return;
+        };
+        let key = img.thumb_key();
+        if self.metadata.as_ref().map(|(k, _)| k) != Some(&key) {
+            self.metadata = img
+                .read_metadata(ui.ctx(), &self.pool)
+                .map(|state| (key, state));
+        }
+        let Some((_, state)) = &mut self.metadata else {
+            ui.spinner();
+            return;
+        };
+        if let MetadataState::Loading(job) = state {
+            let result = match job.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => {
+                    ui.spinner();
+                    return;
+                }
+                Err(TryRecvError::Disconnected) => Err("metadata job is gone".to_owned()),
+            };
+            *state = MetadataState::Done(result);
+        }
+        let MetadataState::Done(result) = state else {
+            return;
+        };
+        let meta = match result {
+            Ok(meta) => meta,
+            Err(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, err.as_str());
+                return;
+            }
+        };
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.metadata_raw, false, "Fields");
+            ui.selectable_value(&mut self.metadata_raw, true, "Raw");
+            if ui
+                .button(ButtonKind::CopyAll.name())
+                .on_hover_text("Copy the raw dump")
+                .clicked()
+            {
+                ui.output_mut(|o| o.copied_text = meta.raw.clone());
+            }
+        });
+        ui.separator();
+        if self.metadata_raw {
+            ScrollArea::both().show(ui, |ui| {
+                ui.add(
+                    TextEdit::multiline(&mut meta.raw.as_str())
+                        .code_editor()
+                        .desired_width(f32::INFINITY),
+                );
+            });
+            return;
+        }
+        ScrollArea::vertical().show(ui, |ui| {
+            for group in &meta.groups {
+                CollapsingHeader::new(&group.namespace)
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        Grid::new(&group.namespace)
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for field in &group.fields {
+                                    ui.strong(&field.name);
+                                    ui.add(Label::new(&field.value).wrap(true));
+                                    if ui
+                                        .small_button(ButtonKind::Copy.name())
+                                        .on_hover_text("Copy value")
+                                        .clicked()
+                                    {
+                                        ui.output_mut(|o| o.copied_text = field.value.clone());
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            }
+        });
+    }
+
+    /// Histogram panel content for the shown image, or the selection in it.
+    pub fn histogram_ui(&mut self, ui: &mut Ui) {
+        let region = self.selection();
+        // a selection being dragged is analysed once released.
+        let dragging = ui.input(|i| i.pointer.any_down());
+        match self.images_sources.get_mut(self.showed_idx) {
+            Some(img) if !dragging => Analysis::update(
+                &mut self.histogram,
+                ui.ctx(),
+                &self.pool,
+                img,
+                region,
+                move |image| Histogram::compute(image, region),
+            ),
+            Some(_) => (),
+            None => {
+                ui.weak("No image opened");
+                return;
+            }
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.histogram_log, "Log scale");
+            ui.checkbox(&mut self.show_clipping, "Show clipping")
+                .on_hover_text("Mark pixels with a channel at 0 in blue, at 255 in red");
+        });
+        let analysis = self.histogram.as_ref();
+        match analysis.and_then(|analysis| analysis.result.as_ref()) {
+            Some(result) => {
+                let of = match analysis.and_then(|analysis| analysis.key.region) {
+                    Some(region) => format!("Selection {} x {}", region.width(), region.height()),
+                    None => "Whole image".to_owned(),
+                };
+                ui.weak(of);
+                histogram::histogram_ui(ui, result, self.histogram_log);
+            }
+            None => {
+                ui.spinner();
+            }
+        }
     }
 
     #[allow(unused)]
@@ -178,7 +1777,7 @@ impl<'img> IVImages<'img> {
         self.showed_idx = self
             .showed_idx
             .saturating_add(1)
-            .min(self.images_sources.len() - 1);
+            .min(self.images_sources.len().saturating_sub(1));
         log::debug!("setting next index on: {}", self.showed_idx);
     }
     #[inline]
@@ -187,17 +1786,435 @@ impl<'img> IVImages<'img> {
         log::debug!("setting prev index on: {}", self.showed_idx);
     }
 
-    pub fn copy_to_clipboard(&self, frame: &Frame, clipboard: &mut Clipboard, ss: ColorImage) {
-        let ss = ss.region(&self.rect, frame.info().native_pixels_per_point);
+    /// Selected region of the shown image, in its pixels.
+    fn selection(&self) -> Option<Rect> {
+        self.selection
+            .filter(|(idx, _)| *idx == self.showed_idx)
+            .map(|(_, rect)| rect)
+    }
+
+    /// Map a point on screen onto the pixels of the shown image, accounting
+    /// for the current zoom, drag and orientation.
+    fn screen_to_image(&self, pos: Pos2) -> Pos2 {
+        let view = ((pos - self.rect.min) / self.rect.size()).to_pos2();
+        let uv = self.orientation.invert(view);
+        (uv.to_vec2() * self.image_size)
+            .to_pos2()
+            .clamp(Pos2::ZERO, self.image_size.to_pos2())
+    }
+
+    /// Pixel of the shown image under `pos`, out of the image when `pos` is.
+    fn screen_to_pixel(&self, pos: Pos2) -> (i64, i64) {
+        let view = ((pos - self.rect.min) / self.rect.size()).to_pos2();
+        let uv = self.orientation.invert(view).to_vec2() * self.image_size;
+        (uv.x.floor() as i64, uv.y.floor() as i64)
+    }
+
+    fn image_to_screen(&self, pos: Pos2) -> Pos2 {
+        let view = self
+            .orientation
+            .apply((pos.to_vec2() / self.image_size).to_pos2());
+        self.rect.min + view.to_vec2() * self.rect.size()
+    }
+
+    /// Size of the shown image as it is turned on screen.
+    #[inline]
+    fn shown_size(&self) -> Vec2 {
+        self.orientation.apply_size(self.image_size)
+    }
+
+    /// Turn the view of the shown image, its pixels are left alone. The turn
+    /// is recorded so it can be undone.
+    fn orient(&mut self, turn: impl FnOnce(Orientation) -> Orientation) {
+        let idx = self.showed_idx;
+        let Some(img) = self.images_sources.get_mut(idx) else {
+            return;
+        };
+        let before = img.orientation;
+        img.orientation = turn(before);
+        if img.orientation != before {
+            let after = img.orientation;
+            self.history.record(Change::Orient { idx, before, after });
+        }
+    }
+
+    /// Context menu entry of the pixel inspector.
+    pub fn inspector_ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.inspector, "Pixel inspector")
+            .on_hover_text("I");
+    }
+
+    /// Context menu entries of the display modes.
+    pub fn display_ui(&mut self, ui: &mut Ui) {
+        self.display.options_ui(ui);
+    }
+
+    /// Texture of the shown image with the tone, channel and alpha modes
+    /// applied, `None` while it is computed or when the modes leave it as is.
+    fn display_texture(&mut self, ctx: &Context) -> Option<TextureHandle> {
+        if self.display.is_plain() {
+            self.display_view = None;
+            return None;
+        }
+        // the last texture stays up while the next one is transformed.
+        if !self.display.same_transform(&self.display_view_options) {
+            Analysis::invalidate(&mut self.display_view);
+            self.display_view_options = self.display;
+        }
+        let img = self.images_sources.get_mut(self.showed_idx)?;
+        let (options, view_ctx, meter) = (self.display, ctx.clone(), self.cache.meter().clone());
+        let color = img.color_transform(&self.color).clone();
+        let max_side = ctx.input(|i| i.max_texture_side);
+        Analysis::update(
+            &mut self.display_view,
+            ctx,
+            &self.pool,
+            img,
+            None,
+            move |image| {
+                let view = options.transform(image, max_side, &color);
+                meter.track(view_ctx.load_texture("display-view", view, TextureOptions::NEAREST))
+            },
+        );
+        let texture = self.display_view.as_ref()?.result.as_ref()?;
+        Some(texture.handle().clone())
+    }
+
+    /// Mark the clipped pixels of the shown image over it.
+    fn paint_clipping(&mut self, ui: &Ui) {
+        let Some(img) = self.images_sources.get_mut(self.showed_idx) else {
+            return;
+        };
+        let (ctx, meter) = (ui.ctx().clone(), self.cache.meter().clone());
+        let max_side = ctx.input(|i| i.max_texture_side);
+        Analysis::update(
+            &mut self.clipping,
+            ui.ctx(),
+            &self.pool,
+            img,
+            None,
+            move |image| {
+                let overlay = histogram::clipping_overlay(image, max_side);
+                meter.track(ctx.load_texture("clipping", overlay, TextureOptions::NEAREST))
+            },
+        );
+        if let Some(texture) = self.clipping.as_ref().and_then(|a| a.result.as_ref()) {
+            self.orientation.paint(
+                ui.painter(),
+                texture.handle().id(),
+                self.rect,
+                Color32::WHITE,
+            );
+        }
+    }
+
+    /// Loupe with the values of the pixel under the cursor, `C` copies its
+    /// hex value and `Shift+C` the whole readout.
+    fn inspect(&mut self, ui: &Ui, res: &Response) {
+        let Some(cursor) = res.hover_pos().filter(|pos| self.rect.contains(*pos)) else {
+            return;
+        };
+        let ctx = ui.ctx();
+        let Some(img) = self.images_sources.get_mut(self.showed_idx) else {
+            return;
+        };
+        // the edited pixels are sampled once baked, not from the texture.
+        if img.edits.is_empty() {
+            self.inspected = None;
+        } else {
+            Analysis::update(&mut self.inspected, ctx, &self.pool, img, None, |image| {
+                Arc::new(image.clone())
+            });
+        }
+        let pixels = match &self.inspected {
+            None => self
+                .images_sources
+                .get(self.showed_idx)
+                .and_then(Img::inspected_pixels),
+            Some(analysis) => analysis
+                .result
+                .as_deref()
+                .filter(|_| analysis.job.is_none())
+                .map(Pixels::Image),
+        };
+        let Some(pixels) = pixels else {
+            egui::show_tooltip_at_pointer(ctx, egui::Id::new("iv_pixel_inspector"), |ui| {
+                ui.weak("Pixel values are not available yet");
+            });
+            return;
+        };
+        let (x, y) = self.screen_to_pixel(cursor);
+        let Some(sample) = pixels.sample(x, y) else {
+            return;
+        };
+        if !ctx.wants_keyboard_input() {
+            if ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::C)) {
+                ctx.output_mut(|o| o.copied_text = sample.hex());
+            } else if ui.input_mut(|i| i.consume_key(Modifiers::SHIFT, Key::C)) {
+                ctx.output_mut(|o| o.copied_text = sample.readout());
+            }
+        }
+        // neighbours are stepped from the centre of the inspected pixel, in
+        // image pixels as laid out on screen.
+        let centre = self.image_to_screen(Pos2::new(x as f32 + 0.5, y as f32 + 0.5));
+        let scale = self.rect.size() / self.shown_size();
+        inspector::loupe_ui(ctx, cursor, &sample, |step| {
+            let (x, y) = self.screen_to_pixel(centre + step * scale);
+            pixels.sample(x, y)
+        });
+    }
+
+    #[inline]
+    pub fn set_apply_exif(&mut self, apply: bool) {
+        self.apply_exif = apply;
+    }
+
+    /// Show the pixels as stored when not `enabled`, instead of converting
+    /// them from their ICC profile to the display one.
+    pub fn set_color_management(&mut self, enabled: bool) {
+        self.color.set_enabled(enabled);
+        Analysis::invalidate(&mut self.display_view);
+    }
+
+    /// Convert to the monitor profile in `path` instead of sRGB.
+    pub fn load_display_profile(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.color.load_display_profile(path)?;
+        self.display_profile_edit = path.display().to_string();
+        Analysis::invalidate(&mut self.display_view);
+        Ok(())
+    }
+
+    /// Color management entries of the image context menu.
+    pub fn color_ui(&mut self, ui: &mut Ui) {
+        let mut enabled = self.color.is_enabled();
+        if ui
+            .checkbox(&mut enabled, "Color management")
+            .on_hover_text("Off shows the pixels as stored, ignoring ICC profiles")
+            .changed()
+        {
+            self.set_color_management(enabled);
+        }
+        match self.images_sources.get(self.showed_idx) {
+            Some(img) if img.icc_profile.is_some() => {
+                ui.weak("Shown image has an embedded profile")
+            }
+            Some(_) => ui.weak("Shown image has no profile, taken as sRGB"),
+            None => ui.weak("No image opened"),
+        };
+        ui.separator();
+        let display = self
+            .color
+            .display_path()
+            .and_then(Path::file_name)
+            .map_or_else(|| "sRGB".into(), |name| name.to_string_lossy());
+        ui.label(format!("Display profile: {display}"));
+        ui.horizontal(|ui| {
+            let edit = ui
+                .text_edit_singleline(&mut self.display_profile_edit)
+                .on_hover_text("Path of the monitor ICC profile");
+            let entered = edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            if ui.button("Load").clicked() || entered {
+                let path = PathBuf::from(self.display_profile_edit.trim());
+                self.display_profile_error = self
+                    .load_display_profile(&path)
+                    .err()
+                    .map(|err| err.to_string());
+            }
+        });
+        if let Some(err) = &self.display_profile_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+        if ui
+            .add_enabled(
+                self.color.display_path().is_some(),
+                egui::Button::new("Use sRGB"),
+            )
+            .clicked()
+        {
+            self.color.reset_display_profile();
+            self.display_profile_error = None;
+            Analysis::invalidate(&mut self.display_view);
+        }
+    }
+
+    /// Rotate and flip entries of the image context menu.
+    pub fn orientation_ui(&mut self, ui: &mut Ui) {
+        for (label, key, turn) in Orientation::TURNS {
+            if ui.button(label).on_hover_text(key).clicked() {
+                self.orient(turn);
+                ui.close_menu();
+            }
+        }
+        let turned = self
+            .images_sources
+            .get(self.showed_idx)
+            .is_some_and(|img| !img.orientation.is_identity());
+        if ui
+            .add_enabled(turned, egui::Button::new("Reset orientation"))
+            .clicked()
+        {
+            self.orient(|_| Orientation::default());
+            ui.close_menu();
+        }
+        ui.checkbox(&mut self.apply_exif, "Apply EXIF orientation")
+            .on_hover_text("Saving writes the pixels the way they are shown");
+    }
+
+    /// Copy the source pixels of the selection to the clipboard at full
+    /// resolution, or the whole image when `whole` is set or nothing is
+    /// selected.
+    pub fn copy_to_clipboard(&self, clipboard: &mut Clipboard, whole: bool) {
+        let Some(img) = self.images_sources.get(self.showed_idx) else {
+            return;
+        };
+        // the frame on screen of animations, as exporting it does.
+        let Some(pixels) = img.shown_pixels() else {
+            log::error!("Failed to copy image to clipboard - (Reason: Image is not loaded yet)");
+            return;
+        };
+        let image = edit::apply_all(&img.edits, &pixels);
+        let image = match self.selection().filter(|_| !whole) {
+            Some(sel) => image.crop_imm(
+                sel.min.x as u32,
+                sel.min.y as u32,
+                sel.width() as u32,
+                sel.height() as u32,
+            ),
+            None => image,
+        };
+        // the selection is in the stored pixels, turned upright once cut.
+        let image = if self.apply_exif {
+            img.exif_orientation.apply_to_image(image)
+        } else {
+            image
+        };
+        let rgba = image.to_rgba8();
         if let Err(err) = clipboard.set_image(ImageData {
-            width: ss.width(),
-            height: ss.height(),
-            bytes: ss.as_raw().into(),
+            width: rgba.width() as usize,
+            height: rgba.height() as usize,
+            bytes: rgba.into_raw().into(),
         }) {
-            log::error!("Failed to copy image to clipboard: (Reason: {err})")
+            log::error!("Failed to copy image to clipboard - (Reason: {err})")
+        }
+    }
+
+    /// Points per image pixel of an image of `image_size` shown in `area`
+    /// with the current zoom mode.
+    fn view_scale(&self, area: Rect, image_size: Vec2) -> f32 {
+        let fit = self.size.unwrap_or(area.size()) / image_size;
+        match self.zoom_mode {
+            ZoomMode::Fit => fit.min_elem(),
+            ZoomMode::Fill => fit.max_elem(),
+            ZoomMode::ActualPixels => 1.0 / self.pixels_per_point,
+            ZoomMode::FitWidth => fit.x,
+            ZoomMode::FitHeight => fit.y,
+            ZoomMode::Free => self.zoom,
+        }
+    }
+
+    /// Screen rect of an image of `image_size` shown in `area`, with the
+    /// current zoom and drag.
+    fn fit_rect(&self, area: Rect, image_size: Vec2) -> Rect {
+        let scale = self.view_scale(area, image_size);
+        Rect::from_center_size(area.center() + self.drag, image_size * scale)
+    }
+
+    pub fn set_zoom_mode(&mut self, mode: ZoomMode) {
+        self.zoom_mode = mode;
+        self.drag = Vec2::ZERO;
+    }
+
+    /// Zoom of the shown image in percent of its actual pixels.
+    fn zoom_percent(&self) -> f32 {
+        self.view_scale(self.view, self.shown_size()) * self.pixels_per_point * 100.0
+    }
+
+    /// Zoom the shown image to `scale` points per pixel, keeping the image
+    /// point under `anchor` in place.
+    fn zoom_at(&mut self, scale: f32, anchor: Pos2) {
+        if self.image_size.x <= 0.0 || self.image_size.y <= 0.0 {
+            return;
+        }
+        let old = self.view_scale(self.view, self.shown_size());
+        let scale = scale.clamp(
+            *ZOOM_RANGE.start() / self.pixels_per_point,
+            *ZOOM_RANGE.end() / self.pixels_per_point,
+        );
+        let center = self.view.center() + self.drag;
+        let center = anchor - (anchor - center) * (scale / old);
+        self.drag = center - self.view.center();
+        self.zoom = scale;
+        self.zoom_mode = ZoomMode::Free;
+    }
+
+    /// Zoom to the next larger step, or smaller when `!larger`.
+    fn step_zoom(&mut self, larger: bool, anchor: Pos2) {
+        let percent = self.zoom_percent();
+        // a little slack so a zoom on a step moves past it.
+        let step = if larger {
+            ZOOM_STEPS.iter().find(|step| **step > percent * 1.01)
+        } else {
+            ZOOM_STEPS.iter().rev().find(|step| **step < percent * 0.99)
+        };
+        if let Some(step) = step {
+            self.zoom_at(step / 100.0 / self.pixels_per_point, anchor);
+        }
+    }
+
+    /// Zoom mode picker with the zoom percentage, for the toolbar.
+    pub fn zoom_ui(&mut self, ui: &mut Ui) {
+        let anchor = self.view.center();
+        if ui.small_button("-").on_hover_text("Zoom out").clicked() {
+            self.step_zoom(false, anchor);
+        }
+        egui::ComboBox::from_id_source("iv_zoom_mode")
+            .selected_text(format!("{:.0}%", self.zoom_percent()))
+            .width(64.0)
+            .show_ui(ui, |ui| {
+                for (mode, name, key) in ZoomMode::ALL {
+                    let label = ui
+                        .selectable_label(self.zoom_mode == mode, name)
+                        .on_hover_text(format!("Key {}", key.name()));
+                    if label.clicked() {
+                        self.set_zoom_mode(mode);
+                    }
+                }
+            });
+        if ui.small_button("+").on_hover_text("Zoom in").clicked() {
+            self.step_zoom(true, anchor);
         }
     }
 
+    /// Paint the image faded out by a running crossfade, returns the opacity
+    /// of the shown one.
+    fn paint_crossfade(&mut self, ui: &Ui, area: Rect) -> f32 {
+        let Some(fade) = &mut self.crossfade else {
+            return 1.0;
+        };
+        let now = ui.input(|i| i.time);
+        let t = ((now - *fade.started.get_or_insert(now)) / fade.duration) as f32;
+        let from = fade.from;
+        if t >= 1.0 || from == self.showed_idx {
+            self.crossfade = None;
+            return 1.0;
+        }
+        let ctx = ui.ctx().clone();
+        let faded = self.images_sources.get_mut(from).map(|img| {
+            let orientation = img.view_orientation(self.apply_exif);
+            (
+                orientation,
+                img.texture(&ctx, &self.pool, self.cache.meter(), &self.color),
+            )
+        });
+        if let Some((orientation, Ok(Some((texture, image_size))))) = faded {
+            let rect = self.fit_rect(area, orientation.apply_size(image_size));
+            let tint = Color32::WHITE.gamma_multiply(1.0 - t);
+            orientation.paint(ui.painter(), texture.id(), rect, tint);
+        }
+        ctx.request_repaint();
+        t
+    }
+
     fn button(&mut self, ui: &mut Ui, rect: Rect, pos: Pos2, align: Align2, text: &str) -> bool {
         let mut clicked = false;
         let btn_rect = ui.painter().text(
@@ -226,61 +2243,189 @@ impl IVImages<'_> {
     pub fn draw(&mut self, ui: &mut Ui) -> Response {
         let res = ui.allocate_rect(ui.min_rect(), Sense::click_and_drag());
 
-        ui.input_mut(|i| {
-            let reset = i.key_released(egui::Key::Num0);
-            let zoom_delta = i.zoom_delta();
-            if i.consume_key(Modifiers::CTRL, Key::J) {
-                self.set_prev();
-            } else if i.consume_key(Modifiers::CTRL, Key::K) {
-                self.set_next();
-            } else if zoom_delta > 1.0 && !reset {
-                self.zoom += Vec2::splat(zoom_delta * 0.1);
-            } else if zoom_delta < 1.0 && !reset {
-                self.zoom -= Vec2::splat(zoom_delta * 0.1);
-            }
-
-            if reset {
-                self.zoom = Vec2::splat(1.0);
-                self.drag = Vec2::ZERO;
-            }
-        });
-
-        let Some(img) = self.images_sources.get_mut(self.showed_idx) else {
-            return res;
-        };
-        let Some(image) = img.image() else {
-            Spinner::new().paint_at(ui, self.rect);
-            return res;
-        };
-
-        let size = self.size.unwrap_or(res.rect.size()) * self.zoom;
-        let tlr = image.load_for_size(ui.ctx(), size);
-        let ui_size = image.calc_size(size, tlr.as_ref().ok().and_then(|t| t.size()));
-        // drag mouse capability
-        self.rect = Rect::from_center_size(res.rect.center(), ui_size);
-        if res.dragged() {
-            self.drag += res.drag_delta();
+        self.view = res.rect;
+        self.pixels_per_point = ui.ctx().pixels_per_point();
+        let zoom_delta = ui.input(|i| i.zoom_delta());
+        let mut step = None;
+        // keys typed into a text field are not shortcuts.
+        if !ui.ctx().wants_keyboard_input() {
+            ui.input_mut(|i| {
+                if i.consume_key(Modifiers::CTRL, Key::J) {
+                    self.set_prev();
+                } else if i.consume_key(Modifiers::CTRL, Key::K) {
+                    self.set_next();
+                } else if i.consume_key(Modifiers::NONE, Key::PlusEquals)
+                    // `+` is typed with shift on most layouts.
+                    || i.consume_key(Modifiers::SHIFT, Key::PlusEquals)
+                {
+                    step = Some(true);
+                } else if i.consume_key(Modifiers::NONE, Key::Minus) {
+                    step = Some(false);
+                } else if i.consume_key(Modifiers::NONE, Key::R) {
+                    self.orient(Orientation::rotate_cw);
+                } else if i.consume_key(Modifiers::SHIFT, Key::R) {
+                    self.orient(Orientation::rotate_ccw);
+                } else if i.consume_key(Modifiers::NONE, Key::H) {
+                    self.orient(Orientation::flip_horizontal);
+                } else if i.consume_key(Modifiers::NONE, Key::V) {
+                    self.orient(Orientation::flip_vertical);
+                } else if i.consume_key(Modifiers::NONE, Key::I) {
+                    self.inspector = !self.inspector;
+                } else if let Some((mode, ..)) = ZoomMode::ALL
+                    .into_iter()
+                    .find(|(_, _, key)| i.consume_key(Modifiers::NONE, *key))
+                {
+                    self.set_zoom_mode(mode);
+                }
+                if i.key_pressed(Key::Escape) {
+                    self.selection = None;
+                }
+            });
         }
-        self.rect = self.rect.translate(self.drag);
-        match tlr {
-            Ok(TexturePoll::Ready { texture }) => {
-                paint_texture_at(ui.painter(), self.rect, image.image_options(), &texture);
+        // zooming follows the cursor, keys zoom around the centre without it.
+        let anchor = ui
+            .input(|i| i.pointer.hover_pos())
+            .filter(|pos| res.rect.contains(*pos))
+            .unwrap_or(res.rect.center());
+
+        let ctx = ui.ctx().clone();
+        self.prefetch(&ctx);
+        let shown = self
+            .images_sources
+            .get_mut(self.showed_idx)
+            .map(|img| img.texture(&ctx, &self.pool, self.cache.meter(), &self.color));
+        match shown {
+            Some(Ok(Some((texture, image_size)))) => {
+                self.image_size = image_size;
+                self.orientation =
+                    self.images_sources[self.showed_idx].view_orientation(self.apply_exif);
+                if zoom_delta != 1.0 {
+                    let scale = self.view_scale(res.rect, self.shown_size()) * zoom_delta;
+                    self.zoom_at(scale, anchor);
+                }
+                if let Some(larger) = step {
+                    self.step_zoom(larger, anchor);
+                }
+                self.rect = self.fit_rect(res.rect, self.shown_size());
+                // shift + drag selects a region, plain drag moves the image.
+                let pointer = res.interact_pointer_pos();
+                if res.drag_started() && ui.input(|i| i.modifiers.shift) {
+                    self.selection_anchor = pointer.map(|pos| self.screen_to_image(pos));
+                }
+                match (self.selection_anchor, pointer) {
+                    (Some(anchor), Some(pos)) if res.dragged() => {
+                        let pos = self.screen_to_image(pos);
+                        // whole pixels, whichever way the view is turned.
+                        let sel = Rect::from_two_pos(anchor, pos);
+                        let sel = Rect::from_min_max(sel.min.floor(), sel.max.ceil());
+                        self.selection = Some((self.showed_idx, sel))
+                            .filter(|(_, sel)| sel.width() >= 1.0 && sel.height() >= 1.0);
+                    }
+                    _ if res.dragged() => {
+                        self.drag += res.drag_delta();
+                        self.rect = self.rect.translate(res.drag_delta());
+                    }
+                    _ => self.selection_anchor = None,
+                }
+                let opacity = self.paint_crossfade(ui, res.rect);
+                let tint = Color32::WHITE.gamma_multiply(opacity);
+                let texture = self.display_texture(&ctx).unwrap_or(texture);
+                self.display
+                    .paint_background(ui.painter(), self.rect, opacity);
+                self.orientation
+                    .paint(ui.painter(), texture.id(), self.rect, tint);
+                if self.show_clipping {
+                    self.paint_clipping(ui);
+                }
+                if let Some(sel) = self.selection() {
+                    let screen = Rect::from_two_pos(
+                        self.image_to_screen(sel.min),
+                        self.image_to_screen(sel.max),
+                    );
+                    let stroke = ui.visuals().selection.stroke;
+                    ui.painter().rect_stroke(screen, Rounding::ZERO, stroke);
+                    ui.painter().text(
+                        screen.left_top(),
+                        Align2::LEFT_BOTTOM,
+                        format!("{} x {}", sel.width(), sel.height()),
+                        TextStyle::Small.resolve(ui.style()),
+                        stroke.color,
+                    );
+                }
+                if self.inspector {
+                    self.inspect(ui, &res);
+                }
             }
-            Ok(TexturePoll::Pending { .. }) => {
-                Spinner::new().paint_at(ui, self.rect);
+            Some(Ok(None)) => {
+                Spinner::new().paint_at(
+                    ui,
+                    Rect::from_center_size(res.rect.center(), Vec2::splat(32.0)),
+                );
+                let progress = self
+                    .images_sources
+                    .get(self.showed_idx)
+                    .and_then(Img::loading_progress);
+                let text = match progress {
+                    Some(None) => "Queued".to_owned(),
+                    Some(Some(p)) if p < 1.0 => format!("Reading {:.0}%", p * 100.0),
+                    Some(Some(_)) => "Decoding".to_owned(),
+                    None => "Loading".to_owned(),
+                };
+                ui.painter().text(
+                    res.rect.center() + Vec2::new(0.0, 24.0),
+                    Align2::CENTER_TOP,
+                    text,
+                    TextStyle::Body.resolve(ui.style()),
+                    ui.visuals().weak_text_color(),
+                );
             }
-            Err(_) => {
+            Some(Err(err)) => {
                 let font_id = TextStyle::Body.resolve(ui.style());
                 ui.painter().text(
-                    self.rect.center(),
+                    res.rect.center(),
                     Align2::CENTER_CENTER,
                     "⚠",
+                    font_id.clone(),
+                    ui.visuals().error_fg_color,
+                );
+                ui.painter().text(
+                    res.rect.center() + Vec2::new(0.0, font_id.size * 1.5),
+                    Align2::CENTER_TOP,
+                    err,
                     font_id,
                     ui.visuals().error_fg_color,
                 );
             }
+            None => (),
         }
-        if res.hovered() {
+        let controls_pos = res.rect.center_bottom() - Vec2::new(0.0, 8.0);
+        let playback = self
+            .images_sources
+            .get_mut(self.showed_idx)
+            .and_then(Img::animation);
+        if let Some(playback) = playback {
+            if !ctx.wants_keyboard_input()
+                && ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Space))
+            {
+                playback.toggle();
+            }
+            let export = egui::Area::new("iv_animation_controls")
+                .fixed_pos(controls_pos)
+                .pivot(Align2::CENTER_BOTTOM)
+                .show(&ctx, |ui| {
+                    egui::Frame::popup(ui.style())
+                        .show(ui, |ui| playback.controls_ui(ui))
+                        .inner
+                })
+                .inner;
+            if export {
+                // keep the exported frame on screen while the dialog is open.
+                playback.seek(playback.current());
+                self.frame_export = true;
+                ctx.request_repaint();
+            }
+        }
+        if res.hovered() && !self.hide_arrows {
             if self.button(
                 ui,
                 res.rect,
@@ -302,10 +2447,252 @@ impl IVImages<'_> {
         }
         res
     }
+
+    /// Must be called once per frame after everything was drawn.
+    pub fn end_frame(&mut self) {
+        self.thumbnails.end_frame();
+    }
+
+    /// Thumbnail of the file at `path`, e.g listed in the file browser,
+    /// generated on the pool like the ones of the filmstrip.
+    pub fn file_thumbnail(
+        &mut self,
+        ctx: &Context,
+        path: &Path,
+        size: ThumbSize,
+        priority: usize,
+    ) -> ThumbPoll {
+        let key = CacheKey::from_path(path);
+        self.thumbnails.get(
+            ctx,
+            &self.pool,
+            &key,
+            size,
+            self.apply_exif,
+            priority,
+            || Some(ThumbSource::Path(path.to_path_buf())),
+        )
+    }
+
+    /// Paint the thumbnail of the image at `idx` fitted in `rect`, returns
+    /// the reason when it could not be generated.
+    fn paint_thumbnail(
+        &mut self,
+        ui: &Ui,
+        idx: usize,
+        rect: Rect,
+        size: ThumbSize,
+    ) -> Option<String> {
+        let img = self.images_sources.get_mut(idx)?;
+        let key = img.thumb_key();
+        let priority = idx.abs_diff(self.showed_idx);
+        let ctx = ui.ctx();
+        let img = &self.images_sources[idx];
+        let poll = self.thumbnails.get(
+            ctx,
+            &self.pool,
+            &key,
+            size,
+            self.apply_exif,
+            priority,
+            || img.thumb_source(ctx),
+        );
+        match poll {
+            ThumbPoll::Ready(texture) => {
+                let size = texture.size_vec2();
+                let scale = (rect.width() / size.x).min(rect.height() / size.y);
+                ui.painter().image(
+                    texture.id(),
+                    Rect::from_center_size(rect.center(), size * scale),
+                    Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                    Color32::WHITE,
+                );
+            }
+            ThumbPoll::Pending => {
+                Spinner::new()
+                    .paint_at(ui, Rect::from_center_size(rect.center(), rect.size() * 0.4));
+            }
+            ThumbPoll::Failed(err) => {
+                ui.painter().text(
+                    rect.center(),
+                    Align2::CENTER_CENTER,
+                    "⚠",
+                    TextStyle::Body.resolve(ui.style()),
+                    ui.visuals().error_fg_color,
+                );
+                return Some(err);
+            }
+        }
+        None
+    }
+
+    /// Row of thumbnails of every image, click one to show it or drag it to
+    /// another place in the list.
+    pub fn filmstrip_ui(&mut self, ui: &mut Ui) {
+        let scroll_to = self.filmstrip_shown != Some(self.showed_idx);
+        self.filmstrip_shown = Some(self.showed_idx);
+        let mut clicked = None;
+        let mut drop_at = None;
+        ScrollArea::horizontal()
+            .id_source("filmstrip_scroll")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for idx in 0..self.images_sources.len() {
+                        let (rect, res) = ui.allocate_exact_size(
+                            Vec2::splat(FILMSTRIP_SIDE),
+                            Sense::click_and_drag(),
+                        );
+                        // only the visible thumbnails are generated.
+                        let failed = ui
+                            .is_rect_visible(rect)
+                            .then(|| self.paint_thumbnail(ui, idx, rect, ThumbSize::Normal))
+                            .flatten();
+                        if idx == self.showed_idx {
+                            let stroke = ui.visuals().selection.stroke;
+                            ui.painter().rect_stroke(rect, Rounding::same(2.0), stroke);
+                            if scroll_to {
+                                res.scroll_to_me(Some(Align::Center));
+                            }
+                        }
+                        if res.drag_started() {
+                            self.filmstrip_dragged = Some(idx);
+                        }
+                        if self.filmstrip_dragged.is_some_and(|from| from != idx)
+                            && ui.rect_contains_pointer(rect)
+                        {
+                            drop_at = Some(idx);
+                            let x = if self.filmstrip_dragged < Some(idx) {
+                                rect.right()
+                            } else {
+                                rect.left()
+                            };
+                            ui.painter()
+                                .vline(x, rect.y_range(), ui.visuals().selection.stroke);
+                        }
+                        if res.clicked() {
+                            clicked = Some(idx);
+                        }
+                        let name = self.images_sources[idx].name();
+                        match failed {
+                            Some(err) => res.on_hover_text(format!("{name}\n{err}")),
+                            None => res.on_hover_text(name),
+                        };
+                    }
+                });
+            });
+        if self.filmstrip_dragged.is_some() && !ui.input(|i| i.pointer.any_down()) {
+            if let (Some(from), Some(to)) = (self.filmstrip_dragged.take(), drop_at) {
+                self.move_image(from, to);
+            }
+        }
+        if let Some(idx) = clicked {
+            self.show_idx(idx);
+        }
+    }
+
+    fn gallery_select(&mut self, idx: usize, modifiers: Modifiers) {
+        if modifiers.shift {
+            let anchor = self.gallery_anchor.unwrap_or(idx);
+            self.gallery_selected = (anchor.min(idx)..=anchor.max(idx)).collect();
+            return;
+        }
+        if modifiers.command || modifiers.ctrl {
+            if !self.gallery_selected.remove(&idx) {
+                self.gallery_selected.insert(idx);
+            }
+        } else {
+            self.gallery_selected = BTreeSet::from([idx]);
+        }
+        self.gallery_anchor = Some(idx);
+        // the clicked image is the one Delete, Copy or Save act on.
+        self.show_idx(idx);
+        self.gallery_shown = Some(self.showed_idx);
+    }
+
+    /// Grid of thumbnails of every image, only the visible rows are laid out
+    /// and generated. Returns true when an image should be opened in the
+    /// single image view, on double click or Enter.
+    pub fn gallery_ui(&mut self, ui: &mut Ui) -> bool {
+        let mut open = false;
+        ui.horizontal(|ui| {
+            ui.add(Slider::new(&mut self.gallery_side, GALLERY_SIDES).text("Size"));
+            ui.weak(format!(
+                "{} images, {} selected",
+                self.images_sources.len(),
+                self.gallery_selected.len()
+            ));
+        });
+        ui.separator();
+        self.gallery_selected
+            .retain(|idx| *idx < self.images_sources.len());
+
+        let spacing = ui.spacing().item_spacing;
+        let side = self.gallery_side;
+        let thumb_size = if side > ThumbSize::Normal.side() as f32 {
+            ThumbSize::Large
+        } else {
+            ThumbSize::Normal
+        };
+        let columns = ((ui.available_width() + spacing.x) / (side + spacing.x)).max(1.0) as usize;
+        let rows = self.images_sources.len().div_ceil(columns);
+        let mut scroll = ScrollArea::vertical()
+            .id_source("gallery_scroll")
+            .auto_shrink([false; 2]);
+        if self.gallery_shown != Some(self.showed_idx) {
+            self.gallery_shown = Some(self.showed_idx);
+            let row = (self.showed_idx / columns) as f32;
+            scroll = scroll.vertical_scroll_offset(row * (side + spacing.y));
+        }
+        let mut clicked = None;
+        scroll.show_rows(ui, side, rows, |ui, range| {
+            for row in range {
+                ui.horizontal(|ui| {
+                    let start = row * columns;
+                    let end = (start + columns).min(self.images_sources.len());
+                    for idx in start..end {
+                        let (rect, res) = ui.allocate_exact_size(Vec2::splat(side), Sense::click());
+                        if self.gallery_selected.contains(&idx) {
+                            let fill = ui.visuals().selection.bg_fill;
+                            ui.painter().rect_filled(rect, Rounding::same(2.0), fill);
+                        }
+                        let failed = self.paint_thumbnail(ui, idx, rect.shrink(2.0), thumb_size);
+                        if idx == self.showed_idx {
+                            let stroke = ui.visuals().selection.stroke;
+                            ui.painter().rect_stroke(rect, Rounding::same(2.0), stroke);
+                        }
+                        if res.double_clicked() {
+                            open = true;
+                        }
+                        if res.clicked() {
+                            clicked = Some((idx, ui.input(|i| i.modifiers)));
+                        }
+                        let name = self.images_sources[idx].name();
+                        match failed {
+                            Some(err) => res.on_hover_text(format!("{name}\n{err}")),
+                            None => res.on_hover_text(name),
+                        };
+                    }
+                });
+            }
+        });
+        if let Some((idx, modifiers)) = clicked {
+            self.gallery_select(idx, modifiers);
+        }
+        if !ui.ctx().wants_keyboard_input() && ui.input(|i| i.key_pressed(Key::Enter)) {
+            if let Some(idx) = self
+                .gallery_anchor
+                .filter(|idx| self.gallery_selected.contains(idx))
+            {
+                self.show_idx(idx);
+                open = true;
+            }
+        }
+        open && !self.images_sources.is_empty()
+    }
 }
 
 #[inline]
-fn filter_map_images_file(path: PathBuf) -> Option<(ImageFormat, PathBuf)> {
+pub fn filter_map_images_file(path: PathBuf) -> Option<(ImageFormat, PathBuf)> {
     let ext = path.extension()?;
     let fmt = image::ImageFormat::from_extension(ext)?;
     Some((fmt, path))
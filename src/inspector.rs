@@ -0,0 +1,213 @@
+use eframe::{
+    egui::{self, Context, Grid, Order, RichText, Ui},
+    emath::Align2,
+    epaint::{Color32, Pos2, Rect, Rounding, Stroke, Vec2},
+};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+
+/// Pixels on each side of the inspected one in the magnified view.
+const LOUPE_RADIUS: i64 = 5;
+/// Side of a magnified pixel, in points.
+const LOUPE_CELL: f32 = 10.0;
+/// Distance of the loupe from the cursor, in points.
+const LOUPE_OFFSET: Vec2 = Vec2::splat(24.0);
+
+/// Pixels the inspector reads from, the shown animation frame or the
+/// decoded image.
+#[derive(Clone, Copy)]
+pub enum Pixels<'a> {
+    Image(&'a DynamicImage),
+    Frame(&'a RgbaImage),
+}
+
+impl<'a> Pixels<'a> {
+    fn size(self) -> (u32, u32) {
+        match self {
+            Self::Image(image) => image.dimensions(),
+            Self::Frame(frame) => frame.dimensions(),
+        }
+    }
+
+    /// Value of the pixel at `x`, `y`, `None` out of the image.
+    pub fn sample(self, x: i64, y: i64) -> Option<Sample> {
+        let (w, h) = self.size();
+        if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 {
+            return None;
+        }
+        let (x, y) = (x as u32, y as u32);
+        let (rgba8, rgba) = match self {
+            Self::Image(image) => {
+                // float values as stored, 16 bit and HDR included.
+                let rgba = image.crop_imm(x, y, 1, 1).to_rgba32f().get_pixel(0, 0).0;
+                (image.get_pixel(x, y).0, rgba)
+            }
+            Self::Frame(frame) => {
+                let rgba8 = frame.get_pixel(x, y).0;
+                (rgba8, rgba8.map(|c| c as f32 / 255.0))
+            }
+        };
+        Some(Sample {
+            pos: (x, y),
+            rgba8,
+            rgba,
+        })
+    }
+}
+
+/// Value of one source pixel.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub pos: (u32, u32),
+    pub rgba8: [u8; 4],
+    pub rgba: [f32; 4],
+}
+
+impl Sample {
+    pub fn hex(&self) -> String {
+        let [r, g, b, a] = self.rgba8;
+        if a == u8::MAX {
+            format!("#{r:02X}{g:02X}{b:02X}")
+        } else {
+            format!("#{r:02X}{g:02X}{b:02X}{a:02X}")
+        }
+    }
+
+    /// Hue in degrees, saturation and value in `0..=1`, of the sRGB values.
+    pub fn hsv(&self) -> [f32; 3] {
+        let [r, g, b, _] = self.rgba8.map(|c| c as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        [hue, saturation, max]
+    }
+
+    /// CIE L*a*b* of the sRGB values, D65 white point.
+    pub fn lab(&self) -> [f32; 3] {
+        let [r, g, b, _] = self.rgba8.map(|c| srgb_to_linear(c as f32 / 255.0));
+        let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.950_47;
+        let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+        let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / 1.088_83;
+        let f = |t: f32| {
+            if t > 216.0 / 24389.0 {
+                t.cbrt()
+            } else {
+                (24389.0 / 27.0 * t + 16.0) / 116.0
+            }
+        };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+    }
+
+    /// Every readout as text, one per line.
+    pub fn readout(&self) -> String {
+        self.rows()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn rows(&self) -> impl Iterator<Item = (&'static str, String)> {
+        let (x, y) = self.pos;
+        let [r, g, b, a] = self.rgba8;
+        let [rf, gf, bf, af] = self.rgba;
+        let [h, s, v] = self.hsv();
+        let [l, la, lb] = self.lab();
+        [
+            ("Position", format!("{x}, {y}")),
+            ("RGBA", format!("{r} {g} {b} {a}")),
+            ("Float", format!("{rf:.4} {gf:.4} {bf:.4} {af:.4}")),
+            ("Hex", self.hex()),
+            (
+                "HSV",
+                format!("{h:.0}\u{B0} {:.0}% {:.0}%", s * 100.0, v * 100.0),
+            ),
+            ("Lab", format!("{l:.1} {la:.1} {lb:.1}")),
+        ]
+        .into_iter()
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Loupe next to `cursor`: the neighbourhood of the inspected pixel
+/// magnified, laid out as seen on screen, then its readout. `neighbour`
+/// maps a step on screen, in image pixels, to the pixel shown there.
+pub fn loupe_ui(
+    ctx: &Context,
+    cursor: Pos2,
+    sample: &Sample,
+    neighbour: impl Fn(Vec2) -> Option<Sample>,
+) {
+    egui::Area::new("iv_pixel_inspector")
+        .order(Order::Tooltip)
+        .interactable(false)
+        .constrain(true)
+        .fixed_pos(cursor + LOUPE_OFFSET)
+        .pivot(Align2::LEFT_TOP)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                magnified_ui(ui, &neighbour);
+                ui.separator();
+                readout_ui(ui, sample);
+                ui.weak("C copies hex, Shift+C everything");
+            });
+        });
+}
+
+fn magnified_ui(ui: &mut Ui, neighbour: &impl Fn(Vec2) -> Option<Sample>) {
+    let cells = (LOUPE_RADIUS * 2 + 1) as f32;
+    let (rect, _) = ui.allocate_exact_size(Vec2::splat(cells * LOUPE_CELL), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, Rounding::ZERO, ui.visuals().extreme_bg_color);
+    for dy in -LOUPE_RADIUS..=LOUPE_RADIUS {
+        for dx in -LOUPE_RADIUS..=LOUPE_RADIUS {
+            let Some(sample) = neighbour(Vec2::new(dx as f32, dy as f32)) else {
+                continue;
+            };
+            let [r, g, b, a] = sample.rgba8;
+            let min = rect.min
+                + Vec2::new(
+                    (dx + LOUPE_RADIUS) as f32 * LOUPE_CELL,
+                    (dy + LOUPE_RADIUS) as f32 * LOUPE_CELL,
+                );
+            let cell = Rect::from_min_size(min, Vec2::splat(LOUPE_CELL));
+            painter.rect_filled(
+                cell,
+                Rounding::ZERO,
+                Color32::from_rgba_unmultiplied(r, g, b, a),
+            );
+        }
+    }
+    let centre = Rect::from_center_size(rect.center(), Vec2::splat(LOUPE_CELL));
+    painter.rect_stroke(centre, Rounding::ZERO, Stroke::new(1.0, Color32::BLACK));
+    painter.rect_stroke(
+        centre.expand(1.0),
+        Rounding::ZERO,
+        Stroke::new(1.0, Color32::WHITE),
+    );
+}
+
+fn readout_ui(ui: &mut Ui, sample: &Sample) {
+    Grid::new("iv_pixel_readout").num_columns(2).show(ui, |ui| {
+        for (name, value) in sample.rows() {
+            ui.strong(name);
+            ui.label(RichText::new(value).monospace());
+            ui.end_row();
+        }
+    });
+}
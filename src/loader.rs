@@ -0,0 +1,203 @@
+use std::{
+    io::Read,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Condvar, Mutex,
+    },
+};
+
+use eframe::egui::Context;
+
+/// Bytes read from a file between two progress updates.
+const READ_CHUNK: usize = 1024 * 1024;
+
+/// Shared state of a job, used to report progress and cancel it.
+pub struct JobState {
+    cancelled: AtomicBool,
+    started: AtomicBool,
+    /// lower runs first, e.g. the distance from the shown image.
+    priority: AtomicUsize,
+    /// `f32` bits of the fraction of work done.
+    progress: AtomicU32,
+}
+
+impl JobState {
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn set_progress(&self, progress: f32) {
+        self.progress.store(progress.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Pending result of a job submitted to the [`WorkerPool`], the job is
+/// cancelled when the handle is dropped.
+pub struct JobHandle<T> {
+    rx: Receiver<T>,
+    state: Arc<JobState>,
+}
+
+impl<T> JobHandle<T> {
+    #[inline]
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.rx.try_recv()
+    }
+
+    #[inline]
+    pub fn set_priority(&self, priority: usize) {
+        self.state.priority.store(priority, Ordering::Relaxed);
+    }
+
+    /// Fraction of work done, `None` while the job is still queued.
+    pub fn progress(&self) -> Option<f32> {
+        self.state
+            .started
+            .load(Ordering::Relaxed)
+            .then(|| f32::from_bits(self.state.progress.load(Ordering::Relaxed)))
+    }
+}
+
+impl<T> Drop for JobHandle<T> {
+    fn drop(&mut self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Job {
+    state: Arc<JobState>,
+    run: Box<dyn FnOnce(&JobState) + Send>,
+}
+
+struct Shared {
+    queue: Mutex<Vec<Job>>,
+    wakeup: Condvar,
+    shutdown: AtomicBool,
+}
+
+/// Fixed set of threads running jobs by priority, so reading and decoding
+/// never happens on the UI thread.
+pub struct WorkerPool {
+    shared: Arc<Shared>,
+}
+
+impl WorkerPool {
+    pub fn new(name: &str) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(vec![]),
+            wakeup: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+        });
+        let threads = std::thread::available_parallelism().map_or(2, |n| n.get().clamp(1, 4));
+        for n in 0..threads {
+            let shared = shared.clone();
+            if let Err(err) = std::thread::Builder::new()
+                .name(format!("rziv::{name}-{n}"))
+                .spawn(move || worker(&shared))
+            {
+                log::error!("Failed to spawn worker thread - (Reason: {err})");
+            }
+        }
+        Self { shared }
+    }
+
+    /// Queue `work`, it returns `None` when it noticed it was cancelled.
+    pub fn submit<T, F>(&self, ctx: &Context, priority: usize, work: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&JobState) -> Option<T> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let state = Arc::new(JobState {
+            cancelled: AtomicBool::new(false),
+            started: AtomicBool::new(false),
+            priority: AtomicUsize::new(priority),
+            progress: AtomicU32::new(0f32.to_bits()),
+        });
+        let ctx = ctx.clone();
+        let job = Job {
+            state: state.clone(),
+            run: Box::new(move |state| {
+                if let Some(result) = work(state) {
+                    tx.send(result).ok();
+                    ctx.request_repaint();
+                }
+            }),
+        };
+        match self.shared.queue.lock() {
+            Ok(mut queue) => queue.push(job),
+            Err(err) => log::error!("Failed to queue job - (Reason: {err})"),
+        }
+        self.shared.wakeup.notify_one();
+        JobHandle { rx, state }
+    }
+}
+
+impl std::fmt::Debug for WorkerPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let queued = self.shared.queue.lock().map_or(0, |queue| queue.len());
+        f.debug_struct("WorkerPool")
+            .field("queued", &queued)
+            .finish()
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Relaxed);
+        self.shared.wakeup.notify_all();
+    }
+}
+
+fn worker(shared: &Shared) {
+    while let Some(job) = next_job(shared) {
+        job.state.started.store(true, Ordering::Relaxed);
+        (job.run)(&job.state);
+    }
+}
+
+/// Block until a job is queued, taking the one with the lowest priority.
+fn next_job(shared: &Shared) -> Option<Job> {
+    let mut queue = shared.queue.lock().ok()?;
+    loop {
+        if shared.shutdown.load(Ordering::Relaxed) {
+            return None;
+        }
+        queue.retain(|job| !job.state.is_cancelled());
+        let next = queue
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, job)| job.state.priority.load(Ordering::Relaxed))
+            .map(|(idx, _)| idx);
+        if let Some(idx) = next {
+            return Some(queue.swap_remove(idx));
+        }
+        queue = shared.wakeup.wait(queue).ok()?;
+    }
+}
+
+/// Read a whole file in chunks, reporting the fraction read as progress.
+/// Returns `Ok(None)` when the job was cancelled meanwhile.
+pub fn read_with_progress(path: &Path, state: &JobState) -> std::io::Result<Option<Vec<u8>>> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata().map_or(0, |m| m.len() as usize);
+    let mut bytes = Vec::with_capacity(len);
+    let mut chunk = vec![0; READ_CHUNK];
+    loop {
+        if state.is_cancelled() {
+            return Ok(None);
+        }
+        match file.read(&mut chunk)? {
+            0 => break,
+            n => bytes.extend_from_slice(&chunk[..n]),
+        }
+        if len > 0 {
+            state.set_progress((bytes.len() as f32 / len as f32).min(1.0));
+        }
+    }
+    Ok(Some(bytes))
+}
@@ -1,14 +1,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+mod animation;
 mod app;
 mod button;
+mod cache;
+mod clipboard;
+mod display;
+mod edit;
+mod file_browser;
+mod histogram;
+mod history;
+mod icc;
 mod images;
+mod inspector;
+mod loader;
 mod logger;
+mod metadata;
+mod orientation;
+mod save;
+mod slideshow;
+mod thumbnail;
+mod thumbnail_cache;
 
 use app::IVApp;
 use clap::Parser;
 use eframe::{epaint, NativeOptions};
-use std::path::PathBuf;
+use slideshow::SlideshowOptions;
+use std::{path::PathBuf, time::Duration};
 
 #[derive(Debug, Clone, Parser)]
 pub struct CmdLine {
@@ -18,9 +36,57 @@ pub struct CmdLine {
     recursive: bool,
     #[clap(short = 'd', long = "verbose", default_value_t = false)]
     verbose: bool,
+    /// number of images on each side of the shown one decoded ahead of time.
+    #[clap(long, default_value_t = 2)]
+    prefetch: usize,
+    /// memory for decoded images and their textures, in MiB.
+    #[clap(long, default_value_t = 1024)]
+    cache_size: usize,
+    /// start a slideshow changing image at this interval, from `100ms` to `10m`.
+    #[clap(long, value_parser = slideshow::parse_interval)]
+    slideshow: Option<Duration>,
+    /// show the slideshow in random order.
+    #[clap(long, default_value_t = false)]
+    shuffle: bool,
+    /// stop the slideshow at the last image instead of starting over.
+    #[clap(long, default_value_t = false)]
+    no_loop: bool,
+    /// run the slideshow fullscreen.
+    #[clap(long, default_value_t = false)]
+    slideshow_fullscreen: bool,
+    /// length of the crossfade between slideshow images, `0s` to cut.
+    #[clap(long, value_parser = slideshow::parse_duration, default_value = "500ms")]
+    crossfade: Duration,
+    /// start in borderless fullscreen.
+    #[clap(short, long, default_value_t = false)]
+    fullscreen: bool,
+    /// show images on a black background.
+    #[clap(short, long, default_value_t = false)]
+    black_background: bool,
+    /// show images as stored, ignoring their EXIF orientation.
+    #[clap(long, default_value_t = false)]
+    no_exif_orientation: bool,
+    /// show pixels as stored, ignoring embedded ICC profiles.
+    #[clap(long, default_value_t = false)]
+    no_color_management: bool,
+    /// ICC profile of the monitor images are converted to, sRGB otherwise.
+    #[clap(long)]
+    display_profile: Option<PathBuf>,
 }
 
 impl CmdLine {
+    fn slideshow_options(&self) -> SlideshowOptions {
+        SlideshowOptions {
+            interval: self
+                .slideshow
+                .unwrap_or(SlideshowOptions::default().interval),
+            shuffle: self.shuffle,
+            repeat: !self.no_loop,
+            fullscreen: self.slideshow_fullscreen,
+            crossfade: self.crossfade,
+        }
+    }
+
     fn get_files(self) -> Vec<PathBuf> {
         let Some(mut files) = self.files else {
             return get_lists_curr_dir(self.recursive);
@@ -51,9 +117,10 @@ fn main() -> anyhow::Result<()> {
         centered: true,
         initial_window_size: Some(INIT_SIZE_WINDOW),
         min_window_size: Some(INIT_SIZE_WINDOW),
+        fullscreen: cmd.fullscreen,
         ..Default::default()
     };
-    eframe::run_native("IVRZ", no, Box::new(|cc| IVApp::new(cc, cmd.get_files())))
+    eframe::run_native("IVRZ", no, Box::new(|cc| IVApp::new(cc, cmd)))
         .map_err(|err| anyhow::anyhow!("Failed to run naitve window - {err}"))
 }
 
@@ -0,0 +1,463 @@
+use std::{collections::BTreeMap, fmt::Write as _, io::Cursor};
+
+use image::ImageFormat;
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+/// Keyword of the PNG text chunk holding the XMP packet.
+const PNG_XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+#[derive(Debug, Clone)]
+pub struct MetaField {
+    pub name: String,
+    pub value: String,
+}
+
+/// Fields of one namespace, e.g `EXIF GPS` or `XMP dc`.
+#[derive(Debug, Clone)]
+pub struct MetaGroup {
+    pub namespace: String,
+    pub fields: Vec<MetaField>,
+}
+
+/// Every metadata field found in an encoded image.
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub groups: Vec<MetaGroup>,
+    /// all sections as read, one after the other.
+    pub raw: String,
+}
+
+impl Metadata {
+    fn push(&mut self, namespace: impl Into<String>, fields: Vec<MetaField>) {
+        if !fields.is_empty() {
+            self.groups.push(MetaGroup {
+                namespace: namespace.into(),
+                fields,
+            });
+        }
+    }
+
+    fn push_raw(&mut self, section: &str, text: &str) {
+        if !text.trim().is_empty() {
+            writeln!(self.raw, "[{section}]\n{}\n", text.trim_end()).ok();
+        }
+    }
+}
+
+fn field(name: impl Into<String>, value: impl Into<String>) -> MetaField {
+    MetaField {
+        name: name.into(),
+        value: value.into(),
+    }
+}
+
+/// Read the EXIF, XMP, IPTC and PNG text metadata of `bytes`, sections that
+/// are missing or broken are left out.
+pub fn read(bytes: &[u8], fmt: ImageFormat) -> Metadata {
+    let mut meta = Metadata::default();
+    let mut file = vec![
+        field("Format", format!("{fmt:?}")),
+        field("Size", format!("{:.1} KiB", bytes.len() as f64 / 1024.0)),
+    ];
+    match image::io::Reader::with_format(Cursor::new(bytes), fmt).into_dimensions() {
+        Ok((w, h)) => file.push(field("Dimensions", format!("{w} x {h}"))),
+        Err(err) => log::debug!("Failed to read image dimensions - (Reason: {err})"),
+    }
+    let raw = file
+        .iter()
+        .map(|f| format!("{}: {}", f.name, f.value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    meta.push_raw("File", &raw);
+    meta.push("File", file);
+
+    read_exif(bytes, &mut meta);
+    let png_text = if fmt == ImageFormat::Png {
+        read_png_text(bytes)
+    } else {
+        vec![]
+    };
+    let xmp = png_text
+        .iter()
+        .find(|(keyword, _)| keyword == PNG_XMP_KEYWORD)
+        .map(|(_, text)| text.clone())
+        .or_else(|| find_xmp_packet(bytes));
+    if let Some(xmp) = xmp {
+        read_xmp(&xmp, &mut meta);
+    }
+    read_iptc(bytes, &mut meta);
+
+    let png_text = png_text
+        .into_iter()
+        .filter(|(keyword, _)| keyword != PNG_XMP_KEYWORD)
+        .map(|(keyword, text)| field(keyword, text))
+        .collect::<Vec<_>>();
+    let raw = png_text
+        .iter()
+        .map(|f| format!("{}: {}", f.name, f.value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    meta.push_raw("PNG text", &raw);
+    meta.push("PNG text", png_text);
+    meta
+}
+
+fn read_exif(bytes: &[u8], meta: &mut Metadata) {
+    let exif = match exif::Reader::new().read_from_container(&mut Cursor::new(bytes)) {
+        Ok(exif) => exif,
+        Err(exif::Error::NotFound(_)) => return,
+        Err(err) => {
+            log::debug!("Failed to read EXIF - (Reason: {err})");
+            return;
+        }
+    };
+    let mut groups = BTreeMap::<(u16, u8), (&str, Vec<MetaField>)>::new();
+    let mut raw = String::new();
+    for f in exif.fields() {
+        let value = f.display_value().with_unit(&exif).to_string();
+        writeln!(
+            raw,
+            "{} 0x{:04x} {}: {value}",
+            f.ifd_num,
+            f.tag.number(),
+            f.tag
+        )
+        .ok();
+        let (order, namespace) = if f.ifd_num == exif::In::THUMBNAIL {
+            (4, "EXIF Thumbnail")
+        } else {
+            match f.tag.context() {
+                exif::Context::Tiff => (0, "EXIF Image"),
+                exif::Context::Exif => (1, "EXIF Photo"),
+                exif::Context::Gps => (2, "EXIF GPS"),
+                exif::Context::Interop => (3, "EXIF Interop"),
+                _ => (5, "EXIF Other"),
+            }
+        };
+        groups
+            .entry((f.ifd_num.index(), order))
+            .or_insert((namespace, vec![]))
+            .1
+            .push(field(f.tag.to_string(), value));
+    }
+    meta.push_raw("EXIF", &raw);
+    for (namespace, fields) in groups.into_values() {
+        meta.push(namespace, fields);
+    }
+}
+
+/// The `x:xmpmeta` element embedded as is, which is how JPEG, TIFF, WebP
+/// and uncompressed PNG chunks store it.
+fn find_xmp_packet(bytes: &[u8]) -> Option<String> {
+    const START: &[u8] = b"<x:xmpmeta";
+    const END: &[u8] = b"</x:xmpmeta>";
+    let start = bytes.windows(START.len()).position(|w| w == START)?;
+    let len = bytes[start..].windows(END.len()).position(|w| w == END)?;
+    let packet = &bytes[start..start + len + END.len()];
+    Some(String::from_utf8_lossy(packet).into_owned())
+}
+
+fn read_xmp(xml: &str, meta: &mut Metadata) {
+    meta.push_raw("XMP", xml);
+    let doc = match roxmltree::Document::parse(xml) {
+        Ok(doc) => doc,
+        Err(err) => {
+            log::debug!("Failed to parse XMP - (Reason: {err})");
+            return;
+        }
+    };
+    let mut groups = BTreeMap::<String, Vec<MetaField>>::new();
+    let descriptions = doc
+        .descendants()
+        .filter(|node| node.has_tag_name((RDF_NS, "Description")));
+    for desc in descriptions {
+        let namespace = |uri: Option<&str>| {
+            let uri = uri.unwrap_or_default();
+            let prefix = desc.lookup_prefix(uri).unwrap_or(uri);
+            format!("XMP {prefix}")
+        };
+        // simple properties are attributes, the rest are child elements.
+        for attr in desc.attributes() {
+            if attr.namespace() == Some(RDF_NS) {
+                continue;
+            }
+            groups
+                .entry(namespace(attr.namespace()))
+                .or_default()
+                .push(field(attr.name(), attr.value()));
+        }
+        for child in desc.children().filter(|node| node.is_element()) {
+            // lists and structures read as their texts, comma separated.
+            let value = child
+                .descendants()
+                .filter(|node| node.is_text())
+                .filter_map(|node| node.text())
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ");
+            groups
+                .entry(namespace(child.tag_name().namespace()))
+                .or_default()
+                .push(field(child.tag_name().name(), value));
+        }
+    }
+    for (namespace, fields) in groups {
+        meta.push(namespace, fields);
+    }
+}
+
+/// IPTC-IIM stored in the Photoshop resources of JPEG files.
+fn read_iptc(bytes: &[u8], meta: &mut Metadata) {
+    const PHOTOSHOP: &[u8] = b"Photoshop 3.0\0";
+    let Some(start) = bytes.windows(PHOTOSHOP.len()).position(|w| w == PHOTOSHOP) else {
+        return;
+    };
+    let Some(iim) = photoshop_resource(&bytes[start + PHOTOSHOP.len()..], 0x0404) else {
+        return;
+    };
+    let mut fields = Vec::<MetaField>::new();
+    let mut raw = String::new();
+    let mut rest = iim;
+    while let [0x1C, record, dataset, hi, lo, data @ ..] = rest {
+        let len = u16::from_be_bytes([*hi, *lo]) as usize;
+        // extended datasets, longer than 32 KiB, are not used by editors.
+        if len & 0x8000 != 0 || len > data.len() {
+            break;
+        }
+        let (value, next) = data.split_at(len);
+        rest = next;
+        let value = match (record, dataset) {
+            (_, 0) if len == 2 => u16::from_be_bytes([value[0], value[1]]).to_string(),
+            _ => String::from_utf8_lossy(value).into_owned(),
+        };
+        let name = iptc_name(*record, *dataset)
+            .map_or_else(|| format!("{record}:{dataset}"), str::to_owned);
+        writeln!(raw, "{record}:{dataset:03} {name}: {value}").ok();
+        // repeated datasets, like keywords, are listed in one field.
+        match fields.iter_mut().find(|f| f.name == name) {
+            Some(f) => {
+                f.value.push_str(", ");
+                f.value.push_str(&value);
+            }
+            None => fields.push(field(name, value)),
+        }
+    }
+    meta.push_raw("IPTC", &raw);
+    meta.push("IPTC", fields);
+}
+
+/// Data of the Photoshop image resource `id`, resources are `8BIM`, the id,
+/// a padded pascal string name, then the size and the padded data.
+fn photoshop_resource(mut bytes: &[u8], id: u16) -> Option<&[u8]> {
+    loop {
+        let [b'8', b'B', b'I', b'M', hi, lo, name_len, rest @ ..] = bytes else {
+            return None;
+        };
+        // the name length byte and the name are padded to an even size.
+        let name_size = (*name_len as usize + 1 + 1) & !1;
+        let rest = rest.get(name_size - 1..)?;
+        let [a, b, c, d, rest @ ..] = rest else {
+            return None;
+        };
+        let size = u32::from_be_bytes([*a, *b, *c, *d]) as usize;
+        let data = rest.get(..size)?;
+        if u16::from_be_bytes([*hi, *lo]) == id {
+            return Some(data);
+        }
+        bytes = rest.get((size + 1) & !1..)?;
+    }
+}
+
+fn iptc_name(record: u8, dataset: u8) -> Option<&'static str> {
+    Some(match (record, dataset) {
+        (1, 0) => "Envelope Record Version",
+        (1, 90) => "Coded Character Set",
+        (2, 0) => "Record Version",
+        (2, 5) => "Object Name",
+        (2, 10) => "Urgency",
+        (2, 15) => "Category",
+        (2, 20) => "Supplemental Category",
+        (2, 25) => "Keywords",
+        (2, 40) => "Special Instructions",
+        (2, 55) => "Date Created",
+        (2, 60) => "Time Created",
+        (2, 62) => "Digital Creation Date",
+        (2, 63) => "Digital Creation Time",
+        (2, 80) => "By-line",
+        (2, 85) => "By-line Title",
+        (2, 90) => "City",
+        (2, 92) => "Sub-location",
+        (2, 95) => "Province/State",
+        (2, 100) => "Country Code",
+        (2, 101) => "Country",
+        (2, 103) => "Original Transmission Reference",
+        (2, 105) => "Headline",
+        (2, 110) => "Credit",
+        (2, 115) => "Source",
+        (2, 116) => "Copyright Notice",
+        (2, 118) => "Contact",
+        (2, 120) => "Caption/Abstract",
+        (2, 122) => "Writer/Editor",
+        _ => return None,
+    })
+}
+
+/// Keywords and texts of the tEXt, zTXt and iTXt chunks, including those
+/// after the image data.
+fn read_png_text(bytes: &[u8]) -> Vec<(String, String)> {
+    let mut reader = match png::Decoder::new(Cursor::new(bytes)).read_info() {
+        Ok(reader) => reader,
+        Err(err) => {
+            log::debug!("Failed to read PNG chunks - (Reason: {err})");
+            return vec![];
+        }
+    };
+    if let Err(err) = reader.finish() {
+        log::debug!("Failed to read PNG chunks after the image - (Reason: {err})");
+    }
+    let info = reader.info();
+    let mut texts = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect::<Vec<_>>();
+    for chunk in &info.compressed_latin1_text {
+        match chunk.get_text() {
+            Ok(text) => texts.push((chunk.keyword.clone(), text)),
+            Err(err) => log::debug!("Failed to decompress zTXt chunk - (Reason: {err})"),
+        }
+    }
+    for chunk in &info.utf8_text {
+        match chunk.get_text() {
+            Ok(text) => texts.push((chunk.keyword.clone(), text)),
+            Err(err) => log::debug!("Failed to decompress iTXt chunk - (Reason: {err})"),
+        }
+    }
+    texts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XMP: &str = concat!(
+        r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">"#,
+        r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">"#,
+        r#"<rdf:Description xmlns:xmp="http://ns.adobe.com/xap/1.0/" "#,
+        r#"xmlns:dc="http://purl.org/dc/elements/1.1/" xmp:Rating="4">"#,
+        r#"<dc:subject><rdf:Bag><rdf:li>cat</rdf:li><rdf:li>dog</rdf:li></rdf:Bag></dc:subject>"#,
+        r#"</rdf:Description></rdf:RDF></x:xmpmeta>"#,
+    );
+
+    fn fields<'a>(meta: &'a Metadata, namespace: &str) -> Vec<(&'a str, &'a str)> {
+        meta.groups
+            .iter()
+            .filter(|group| group.namespace == namespace)
+            .flat_map(|group| &group.fields)
+            .map(|f| (f.name.as_str(), f.value.as_str()))
+            .collect()
+    }
+
+    /// Photoshop resource `id` holding `data`, with an empty name.
+    fn resource(id: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"8BIM".to_vec();
+        bytes.extend(id.to_be_bytes());
+        bytes.extend([0, 0]);
+        bytes.extend((data.len() as u32).to_be_bytes());
+        bytes.extend(data);
+        if data.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    fn iptc_dataset(record: u8, dataset: u8, value: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0x1C, record, dataset];
+        bytes.extend((value.len() as u16).to_be_bytes());
+        bytes.extend(value);
+        bytes
+    }
+
+    #[test]
+    fn finds_embedded_xmp_packets() {
+        let bytes = [b"junk".as_slice(), XMP.as_bytes(), b"more junk"].concat();
+        assert_eq!(find_xmp_packet(&bytes).as_deref(), Some(XMP));
+        assert_eq!(find_xmp_packet(b"<x:xmpmeta unterminated"), None);
+    }
+
+    #[test]
+    fn reads_xmp_attributes_and_lists() {
+        let mut meta = Metadata::default();
+        read_xmp(XMP, &mut meta);
+        assert_eq!(fields(&meta, "XMP xmp"), [("Rating", "4")]);
+        assert_eq!(fields(&meta, "XMP dc"), [("subject", "cat, dog")]);
+        assert!(meta.raw.starts_with("[XMP]\n"));
+    }
+
+    #[test]
+    fn finds_photoshop_resources() {
+        let bytes = [resource(0x0400, b"odd"), resource(0x0404, b"iptc")].concat();
+        assert_eq!(photoshop_resource(&bytes, 0x0404), Some(&b"iptc"[..]));
+        assert_eq!(photoshop_resource(&bytes, 0x0400), Some(&b"odd"[..]));
+        assert_eq!(photoshop_resource(&bytes, 0x0405), None);
+        assert_eq!(photoshop_resource(&bytes[..bytes.len() - 1], 0x0404), None);
+    }
+
+    #[test]
+    fn reads_iptc_datasets() {
+        let iim = [
+            iptc_dataset(2, 0, &[0, 4]),
+            iptc_dataset(2, 25, b"cat"),
+            iptc_dataset(2, 25, b"dog"),
+            iptc_dataset(2, 200, b"custom"),
+        ]
+        .concat();
+        let bytes = [b"Photoshop 3.0\0".to_vec(), resource(0x0404, &iim)].concat();
+        let mut meta = Metadata::default();
+        read_iptc(&bytes, &mut meta);
+        assert_eq!(
+            fields(&meta, "IPTC"),
+            [
+                ("Record Version", "4"),
+                ("Keywords", "cat, dog"),
+                ("2:200", "custom")
+            ]
+        );
+        assert_eq!(iptc_name(2, 120), Some("Caption/Abstract"));
+        assert_eq!(iptc_name(9, 9), None);
+    }
+
+    #[test]
+    fn reads_png_text_chunks() {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, 1, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder
+            .add_text_chunk("Title".to_owned(), "plain".to_owned())
+            .unwrap();
+        encoder
+            .add_ztxt_chunk("Comment".to_owned(), "zipped".to_owned())
+            .unwrap();
+        encoder
+            .add_itxt_chunk(PNG_XMP_KEYWORD.to_owned(), XMP.to_owned())
+            .unwrap();
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[0])
+            .unwrap();
+
+        let texts = read_png_text(&bytes);
+        assert!(texts.contains(&("Title".to_owned(), "plain".to_owned())));
+        assert!(texts.contains(&("Comment".to_owned(), "zipped".to_owned())));
+        assert!(texts.contains(&(PNG_XMP_KEYWORD.to_owned(), XMP.to_owned())));
+
+        let meta = read(&bytes, ImageFormat::Png);
+        assert_eq!(fields(&meta, "XMP xmp"), [("Rating", "4")]);
+        assert_eq!(
+            fields(&meta, "PNG text"),
+            [("Title", "plain"), ("Comment", "zipped")]
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use std::io::Cursor;
+
+use eframe::{
+    egui::{Painter, TextureId},
+    epaint::{Color32, Mesh, Pos2, Rect, Shape, Vec2, Vertex},
+};
+use image::DynamicImage;
+
+/// How an image is turned for viewing, without touching its pixels: the
+/// image is mirrored horizontally first when `mirrored`, then rotated by
+/// `quarter_turns` clockwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Orientation {
+    quarter_turns: u8,
+    mirrored: bool,
+}
+
+type Turn = fn(Orientation) -> Orientation;
+
+impl Orientation {
+    /// Ways to turn the view, with their names and keys.
+    pub const TURNS: [(&'static str, &'static str, Turn); 4] = [
+        ("\u{21BB} Rotate right", "R", Self::rotate_cw),
+        ("\u{21BA} Rotate left", "Shift+R", Self::rotate_ccw),
+        ("\u{2194} Flip horizontal", "H", Self::flip_horizontal),
+        ("\u{2195} Flip vertical", "V", Self::flip_vertical),
+    ];
+
+    /// Orientation the EXIF `Orientation` tag of `bytes` asks for, JPEG,
+    /// TIFF, PNG, WebP and HEIF/AVIF containers are read.
+    pub fn from_exif(bytes: &[u8]) -> Self {
+        let Ok(exif) = exif::Reader::new().read_from_container(&mut Cursor::new(bytes)) else {
+            return Self::default();
+        };
+        exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            .map_or_else(Self::default, Self::from_exif_value)
+    }
+
+    /// Map the EXIF `Orientation` values, 1 being upright.
+    fn from_exif_value(value: u32) -> Self {
+        let (mirrored, quarter_turns) = match value {
+            2 => (true, 0),
+            3 => (false, 2),
+            4 => (true, 2),
+            5 => (true, 3),
+            6 => (false, 1),
+            7 => (true, 1),
+            8 => (false, 3),
+            _ => (false, 0),
+        };
+        Self {
+            quarter_turns,
+            mirrored,
+        }
+    }
+
+    #[inline]
+    pub fn is_identity(self) -> bool {
+        self == Self::default()
+    }
+
+    pub fn rotate_cw(self) -> Self {
+        Self {
+            quarter_turns: (self.quarter_turns + 1) % 4,
+            ..self
+        }
+    }
+
+    pub fn rotate_ccw(self) -> Self {
+        Self {
+            quarter_turns: (self.quarter_turns + 3) % 4,
+            ..self
+        }
+    }
+
+    /// Mirror the view left to right, whatever the current rotation is.
+    pub fn flip_horizontal(self) -> Self {
+        Self {
+            quarter_turns: (4 - self.quarter_turns) % 4,
+            mirrored: !self.mirrored,
+        }
+    }
+
+    /// Mirror the view top to bottom, whatever the current rotation is.
+    pub fn flip_vertical(self) -> Self {
+        self.flip_horizontal().rotate_cw().rotate_cw()
+    }
+
+    /// `self` followed by `next`.
+    pub fn then(self, next: Self) -> Self {
+        let mut turned = if next.mirrored {
+            self.flip_horizontal()
+        } else {
+            self
+        };
+        for _ in 0..next.quarter_turns {
+            turned = turned.rotate_cw();
+        }
+        turned
+    }
+
+    /// Turn the pixels of `image` for real, e.g before saving.
+    pub fn apply_to_image(self, image: DynamicImage) -> DynamicImage {
+        let image = if self.mirrored { image.fliph() } else { image };
+        match self.quarter_turns {
+            1 => image.rotate90(),
+            2 => image.rotate180(),
+            3 => image.rotate270(),
+            _ => image,
+        }
+    }
+
+    /// Size of an image of `size` once turned.
+    pub fn apply_size(self, size: Vec2) -> Vec2 {
+        if self.quarter_turns % 2 == 1 {
+            Vec2::new(size.y, size.x)
+        } else {
+            size
+        }
+    }
+
+    /// Map a point of the image, in fractions of its size, to the turned
+    /// view.
+    pub fn apply(self, uv: Pos2) -> Pos2 {
+        let mut p = if self.mirrored {
+            Pos2::new(1.0 - uv.x, uv.y)
+        } else {
+            uv
+        };
+        for _ in 0..self.quarter_turns {
+            p = Pos2::new(1.0 - p.y, p.x);
+        }
+        p
+    }
+
+    /// Map a point of the turned view, in fractions of its size, back to
+    /// the image.
+    pub fn invert(self, p: Pos2) -> Pos2 {
+        let mut uv = p;
+        for _ in 0..self.quarter_turns {
+            uv = Pos2::new(uv.y, 1.0 - uv.x);
+        }
+        if self.mirrored {
+            uv.x = 1.0 - uv.x;
+        }
+        uv
+    }
+
+    /// Paint `texture` turned into `rect`, by moving the texture coordinates
+    /// of the corners.
+    pub fn paint(self, painter: &Painter, texture: TextureId, rect: Rect, tint: Color32) {
+        let mut mesh = Mesh::with_texture(texture);
+        for corner in [
+            Pos2::ZERO,
+            Pos2::new(1.0, 0.0),
+            Pos2::new(1.0, 1.0),
+            Pos2::new(0.0, 1.0),
+        ] {
+            let pos = rect.min + corner.to_vec2() * rect.size();
+            mesh.vertices.push(Vertex {
+                pos,
+                uv: self.invert(corner),
+                color: tint,
+            });
+        }
+        mesh.add_triangle(0, 1, 2);
+        mesh.add_triangle(0, 2, 3);
+        painter.add(Shape::mesh(mesh));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbImage;
+
+    use super::*;
+
+    fn all() -> impl Iterator<Item = Orientation> {
+        (1..=8).map(Orientation::from_exif_value)
+    }
+
+    /// 2x1 image with a distinct pixel per column.
+    fn image() -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(2, 1, |x, _| image::Rgb([x as u8, 0, 0])))
+    }
+
+    #[test]
+    fn exif_values_are_distinct() {
+        let orientations = all().collect::<Vec<_>>();
+        assert!(orientations[0].is_identity());
+        for (i, a) in orientations.iter().enumerate() {
+            for b in &orientations[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        assert!(Orientation::from_exif_value(0).is_identity());
+        assert!(Orientation::from_exif(b"not an image").is_identity());
+    }
+
+    #[test]
+    fn turns_undo_each_other() {
+        for o in all() {
+            assert_eq!(o.rotate_cw().rotate_ccw(), o);
+            assert_eq!(o.flip_horizontal().flip_horizontal(), o);
+            assert_eq!(o.flip_vertical().flip_vertical(), o);
+            assert_eq!(o.rotate_cw().rotate_cw().rotate_cw().rotate_cw(), o);
+        }
+    }
+
+    #[test]
+    fn then_matches_turning_in_sequence() {
+        let cw = Orientation::default().rotate_cw();
+        let flip = Orientation::default().flip_horizontal();
+        for o in all() {
+            assert_eq!(o.then(Orientation::default()), o);
+            assert_eq!(Orientation::default().then(o), o);
+            assert_eq!(o.then(cw), o.rotate_cw());
+            assert_eq!(o.then(flip), o.flip_horizontal());
+        }
+    }
+
+    #[test]
+    fn points_round_trip() {
+        let uv = Pos2::new(0.25, 0.75);
+        for a in all() {
+            assert_eq!(a.invert(a.apply(uv)), uv);
+            assert_eq!(a.apply(a.invert(uv)), uv);
+            for b in all() {
+                assert_eq!(a.then(b).apply(uv), b.apply(a.apply(uv)));
+            }
+        }
+    }
+
+    #[test]
+    fn points_follow_the_turns() {
+        let top_left = Pos2::ZERO;
+        let cw = Orientation::default().rotate_cw();
+        assert_eq!(cw.apply(top_left), Pos2::new(1.0, 0.0));
+        let flip = Orientation::default().flip_vertical();
+        assert_eq!(flip.apply(top_left), Pos2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn image_and_size_follow_the_turns() {
+        let size = Vec2::new(2.0, 1.0);
+        for o in all() {
+            let turned = o.apply_to_image(image());
+            let expected = o.apply_size(size);
+            assert_eq!(turned.width() as f32, expected.x);
+            assert_eq!(turned.height() as f32, expected.y);
+        }
+        let cw = Orientation::default().rotate_cw().apply_to_image(image());
+        assert_eq!(cw.to_rgb8().get_pixel(0, 1).0[0], 1);
+        let flip = Orientation::default()
+            .flip_horizontal()
+            .apply_to_image(image());
+        assert_eq!(flip.to_rgb8().get_pixel(0, 0).0[0], 1);
+    }
+}
@@ -0,0 +1,736 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use eframe::{
+    egui::{Button, Checkbox, ComboBox, Context, Key, ScrollArea, Slider, TextEdit, Ui, Window},
+    epaint::Vec2,
+};
+use image::{
+    codecs::{
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType, PngEncoder},
+        pnm::{PnmSubtype, SampleEncoding},
+        webp::WebPEncoder,
+    },
+    DynamicImage, GrayImage, ImageFormat, ImageOutputFormat,
+};
+
+use crate::{button::ButtonKind, file_browser::list_subdirs, icc};
+
+/// Every format with an encoder, in the order of the README table.
+pub const ENCODABLE_FORMATS: &[ImageFormat] = &[
+    #[cfg(feature = "avif")]
+    ImageFormat::Avif,
+    ImageFormat::Bmp,
+    ImageFormat::Farbfeld,
+    ImageFormat::Gif,
+    ImageFormat::Ico,
+    ImageFormat::Jpeg,
+    ImageFormat::OpenExr,
+    ImageFormat::Png,
+    ImageFormat::Pnm,
+    ImageFormat::Qoi,
+    ImageFormat::Tga,
+    ImageFormat::Tiff,
+    ImageFormat::WebP,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodeOptions {
+    #[cfg(feature = "avif")]
+    Avif {
+        quality: u8,
+        speed: u8,
+    },
+    Bmp,
+    Farbfeld,
+    Gif,
+    Ico,
+    Jpeg {
+        quality: u8,
+    },
+    OpenExr,
+    Png {
+        compression: CompressionType,
+        filter: FilterType,
+    },
+    Pnm {
+        subtype: PnmSubtype,
+    },
+    Qoi,
+    Tga,
+    Tiff,
+    /// only the lossless VP8L encoder is built in, lossy WebP needs libwebp.
+    WebP {
+        lossless: bool,
+    },
+}
+
+impl EncodeOptions {
+    /// Default options for `fmt`, or `None` when there is no encoder for it.
+    pub fn default_for(fmt: ImageFormat) -> Option<Self> {
+        Some(match fmt {
+            #[cfg(feature = "avif")]
+            ImageFormat::Avif => Self::Avif {
+                quality: 80,
+                speed: 4,
+            },
+            ImageFormat::Bmp => Self::Bmp,
+            ImageFormat::Farbfeld => Self::Farbfeld,
+            ImageFormat::Gif => Self::Gif,
+            ImageFormat::Ico => Self::Ico,
+            ImageFormat::Jpeg => Self::Jpeg { quality: 90 },
+            ImageFormat::OpenExr => Self::OpenExr,
+            ImageFormat::Png => Self::Png {
+                compression: CompressionType::Default,
+                filter: FilterType::Adaptive,
+            },
+            ImageFormat::Pnm => Self::Pnm {
+                subtype: PnmSubtype::ArbitraryMap,
+            },
+            ImageFormat::Qoi => Self::Qoi,
+            ImageFormat::Tga => Self::Tga,
+            ImageFormat::Tiff => Self::Tiff,
+            ImageFormat::WebP => Self::WebP { lossless: true },
+            _ => return None,
+        })
+    }
+
+    /// Default options to overwrite the file at `path` in `fmt` with, PNM
+    /// files keep their subtype and sample encoding.
+    pub fn default_for_file(fmt: ImageFormat, path: &Path) -> Option<Self> {
+        match Self::default_for(fmt)? {
+            Self::Pnm { .. } => {
+                let mut magic = [0; 2];
+                let header = fs::File::open(path)
+                    .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut magic));
+                let subtype = header
+                    .ok()
+                    .and_then(|()| pnm_subtype_from_magic(magic))
+                    .or_else(|| pnm_subtype_from_extension(path))
+                    .unwrap_or(PnmSubtype::ArbitraryMap);
+                Some(Self::Pnm { subtype })
+            }
+            options => Some(options),
+        }
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        match self {
+            #[cfg(feature = "avif")]
+            Self::Avif { .. } => ImageFormat::Avif,
+            Self::Bmp => ImageFormat::Bmp,
+            Self::Farbfeld => ImageFormat::Farbfeld,
+            Self::Gif => ImageFormat::Gif,
+            Self::Ico => ImageFormat::Ico,
+            Self::Jpeg { .. } => ImageFormat::Jpeg,
+            Self::OpenExr => ImageFormat::OpenExr,
+            Self::Png { .. } => ImageFormat::Png,
+            Self::Pnm { .. } => ImageFormat::Pnm,
+            Self::Qoi => ImageFormat::Qoi,
+            Self::Tga => ImageFormat::Tga,
+            Self::Tiff => ImageFormat::Tiff,
+            Self::WebP { .. } => ImageFormat::WebP,
+        }
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        match self {
+            #[cfg(feature = "avif")]
+            Self::Avif { quality, speed } => {
+                ui.add(Slider::new(quality, 1..=100).text("Quality"));
+                ui.add(Slider::new(speed, 1..=10).text("Speed"));
+            }
+            Self::Jpeg { quality } => {
+                ui.add(Slider::new(quality, 1..=100).text("Quality"));
+            }
+            Self::Png {
+                compression,
+                filter,
+            } => {
+                ComboBox::from_label("Compression")
+                    .selected_text(format!("{compression:?}"))
+                    .show_ui(ui, |ui| {
+                        for c in [
+                            CompressionType::Fast,
+                            CompressionType::Default,
+                            CompressionType::Best,
+                        ] {
+                            ui.selectable_value(compression, c, format!("{c:?}"));
+                        }
+                    });
+                ComboBox::from_label("Filter")
+                    .selected_text(format!("{filter:?}"))
+                    .show_ui(ui, |ui| {
+                        for f in [
+                            FilterType::NoFilter,
+                            FilterType::Sub,
+                            FilterType::Up,
+                            FilterType::Avg,
+                            FilterType::Paeth,
+                            FilterType::Adaptive,
+                        ] {
+                            ui.selectable_value(filter, f, format!("{f:?}"));
+                        }
+                    });
+            }
+            Self::Pnm { subtype } => {
+                ComboBox::from_label("Subtype")
+                    .selected_text(pnm_subtype_name(*subtype))
+                    .show_ui(ui, |ui| {
+                        for s in [
+                            PnmSubtype::ArbitraryMap,
+                            PnmSubtype::Pixmap(SampleEncoding::Binary),
+                            PnmSubtype::Pixmap(SampleEncoding::Ascii),
+                            PnmSubtype::Graymap(SampleEncoding::Binary),
+                            PnmSubtype::Graymap(SampleEncoding::Ascii),
+                        ] {
+                            ui.selectable_value(subtype, s, pnm_subtype_name(s));
+                        }
+                    });
+            }
+            Self::WebP { lossless } => {
+                ui.add_enabled(false, Checkbox::new(lossless, "Lossless"))
+                    .on_disabled_hover_text("Lossy WebP encoding is not built in");
+            }
+            _ => {
+                ui.weak("No options for this format");
+            }
+        }
+    }
+
+    /// Convert `image` into a color type the encoder accepts.
+    fn convert(&self, image: &DynamicImage) -> DynamicImage {
+        use DynamicImage as D;
+        let color = image.color();
+        let (gray, alpha) = (!color.has_color(), color.has_alpha());
+        let depth = color.bytes_per_pixel() / color.channel_count();
+        let to_8bit = |gray: bool, alpha: bool| match (gray, alpha) {
+            (true, false) => D::ImageLuma8(image.to_luma8()),
+            (true, true) => D::ImageLumaA8(image.to_luma_alpha8()),
+            (false, false) => D::ImageRgb8(image.to_rgb8()),
+            (false, true) => D::ImageRgba8(image.to_rgba8()),
+        };
+        match self {
+            Self::Jpeg { .. } => to_8bit(gray, false),
+            Self::Bmp | Self::Tga => to_8bit(gray, alpha),
+            // icons are PNG inside, which readers only take as RGBA.
+            Self::Ico => to_8bit(false, true),
+            // the encoder has no gray with alpha, and keeps 16 bits as they are.
+            Self::Tiff if depth > 1 => match (gray && !alpha, alpha) {
+                (true, _) => D::ImageLuma16(image.to_luma16()),
+                (false, false) => D::ImageRgb16(image.to_rgb16()),
+                (false, true) => D::ImageRgba16(image.to_rgba16()),
+            },
+            Self::Tiff => to_8bit(gray && !alpha, alpha),
+            #[cfg(feature = "avif")]
+            Self::Avif { .. } => to_8bit(false, alpha),
+            Self::Gif | Self::Qoi | Self::WebP { .. } => to_8bit(false, alpha),
+            Self::Farbfeld => D::ImageRgba16(image.to_rgba16()),
+            Self::OpenExr if alpha => D::ImageRgba32F(image.to_rgba32f()),
+            Self::OpenExr => D::ImageRgb32F(image.to_rgb32f()),
+            Self::Pnm {
+                subtype: PnmSubtype::Pixmap(_),
+            } if depth == 1 => D::ImageRgb8(image.to_rgb8()),
+            Self::Pnm {
+                subtype: PnmSubtype::Pixmap(_),
+            } => D::ImageRgb16(image.to_rgb16()),
+            // black below mid gray, white above.
+            Self::Pnm {
+                subtype: PnmSubtype::Bitmap(_),
+            } => {
+                let mut luma = image.to_luma8();
+                for pixel in luma.pixels_mut() {
+                    pixel.0[0] = if pixel.0[0] < 128 { 0 } else { 255 };
+                }
+                D::ImageLuma8(luma)
+            }
+            Self::Pnm {
+                subtype: PnmSubtype::Graymap(_),
+            } if depth == 1 => D::ImageLuma8(image.to_luma8()),
+            Self::Pnm {
+                subtype: PnmSubtype::Graymap(_),
+            } => D::ImageLuma16(image.to_luma16()),
+            Self::Png { .. } | Self::Pnm { .. } if depth == 4 => match alpha {
+                true => D::ImageRgba16(image.to_rgba16()),
+                false => D::ImageRgb16(image.to_rgb16()),
+            },
+            Self::Png { .. } | Self::Pnm { .. } => image.clone(),
+        }
+    }
+
+    /// Encode `image`, already converted, into memory with these options.
+    fn encode(&self, image: &DynamicImage) -> image::ImageResult<Vec<u8>> {
+        let mut buffer = Cursor::new(Vec::new());
+        match *self {
+            Self::Jpeg { quality } => {
+                image.write_with_encoder(JpegEncoder::new_with_quality(&mut buffer, quality))?
+            }
+            Self::Png {
+                compression,
+                filter,
+            } => image.write_with_encoder(PngEncoder::new_with_quality(
+                &mut buffer,
+                compression,
+                filter,
+            ))?,
+            Self::WebP { .. } => {
+                image.write_with_encoder(WebPEncoder::new_lossless(&mut buffer))?
+            }
+            #[cfg(feature = "avif")]
+            Self::Avif { quality, speed } => image.write_with_encoder(
+                image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                    &mut buffer,
+                    speed,
+                    quality,
+                ),
+            )?,
+            Self::Pnm {
+                subtype: PnmSubtype::Bitmap(encoding),
+            } => return Ok(encode_pbm(&image.to_luma8(), encoding)),
+            Self::Pnm { subtype } => {
+                image.write_to(&mut buffer, ImageOutputFormat::Pnm(subtype))?
+            }
+            _ => image.write_to(&mut buffer, self.format())?,
+        }
+        Ok(buffer.into_inner())
+    }
+
+    /// Encode `image` and write it to `path`, the file is only touched once
+    /// encoding succeeded. `icc_profile` is embedded when the format can
+    /// carry it and it describes the encoded pixels, e.g not a gray profile
+    /// for a format that only stores RGB. Returns whether it was embedded.
+    pub fn save(
+        &self,
+        image: &DynamicImage,
+        path: &Path,
+        icc_profile: Option<&[u8]>,
+    ) -> anyhow::Result<bool> {
+        let image = self.convert(image);
+        let mut bytes = self.encode(&image).map_err(|err| {
+            anyhow::anyhow!(
+                "Failed to encode image as {:?} - (Reason: {err})",
+                self.format()
+            )
+        })?;
+        let icc_profile = icc_profile.filter(|profile| {
+            icc::can_embed(self.format()) && icc::describes(profile, image.color())
+        });
+        if let Some(profile) = icc_profile {
+            bytes = icc::embed(&bytes, self.format(), profile)
+                .map_err(|err| anyhow::anyhow!("Failed to embed ICC profile - (Reason: {err})"))?;
+        }
+        fs::write(path, bytes).map_err(|err| {
+            anyhow::anyhow!(
+                "Failed to write image to {} - (Reason: {err})",
+                path.display()
+            )
+        })?;
+        Ok(icc_profile.is_some())
+    }
+}
+
+/// PBM of `image`, already black and white, written by hand since the
+/// encoder of `image` refuses samples that all fit in one bit. Black is 1.
+fn encode_pbm(image: &GrayImage, encoding: SampleEncoding) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let rows = image.as_raw().chunks(width.max(1) as usize);
+    match encoding {
+        SampleEncoding::Binary => {
+            let mut out = format!("P4\n{width} {height}\n").into_bytes();
+            for row in rows {
+                // rows are padded to whole bytes, leftmost pixel in the high bit.
+                out.extend(row.chunks(8).map(|pixels| {
+                    pixels
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, sample)| **sample == 0)
+                        .fold(0u8, |byte, (i, _)| byte | 0x80 >> i)
+                }));
+            }
+            out
+        }
+        SampleEncoding::Ascii => {
+            let mut out = format!("P1\n{width} {height}\n");
+            for row in rows {
+                // lines of plain PBM are kept under 70 characters.
+                for line in row.chunks(34) {
+                    for sample in line {
+                        out.push_str(if *sample == 0 { "1 " } else { "0 " });
+                    }
+                    out.push('\n');
+                }
+            }
+            out.into_bytes()
+        }
+    }
+}
+
+/// Subtype of a PNM file from its `P1` to `P7` magic number.
+fn pnm_subtype_from_magic(magic: [u8; 2]) -> Option<PnmSubtype> {
+    use SampleEncoding::{Ascii, Binary};
+    Some(match &magic {
+        b"P1" => PnmSubtype::Bitmap(Ascii),
+        b"P2" => PnmSubtype::Graymap(Ascii),
+        b"P3" => PnmSubtype::Pixmap(Ascii),
+        b"P4" => PnmSubtype::Bitmap(Binary),
+        b"P5" => PnmSubtype::Graymap(Binary),
+        b"P6" => PnmSubtype::Pixmap(Binary),
+        b"P7" => PnmSubtype::ArbitraryMap,
+        _ => return None,
+    })
+}
+
+fn pnm_subtype_from_extension(path: &Path) -> Option<PnmSubtype> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "pbm" => PnmSubtype::Bitmap(SampleEncoding::Binary),
+        "pgm" => PnmSubtype::Graymap(SampleEncoding::Binary),
+        "ppm" => PnmSubtype::Pixmap(SampleEncoding::Binary),
+        "pam" => PnmSubtype::ArbitraryMap,
+        _ => return None,
+    })
+}
+
+fn pnm_subtype_name(subtype: PnmSubtype) -> &'static str {
+    match subtype {
+        PnmSubtype::Bitmap(SampleEncoding::Binary) => "PBM (binary)",
+        PnmSubtype::Bitmap(SampleEncoding::Ascii) => "PBM (ascii)",
+        PnmSubtype::Graymap(SampleEncoding::Binary) => "PGM (binary)",
+        PnmSubtype::Graymap(SampleEncoding::Ascii) => "PGM (ascii)",
+        PnmSubtype::Pixmap(SampleEncoding::Binary) => "PPM (binary)",
+        PnmSubtype::Pixmap(SampleEncoding::Ascii) => "PPM (ascii)",
+        PnmSubtype::ArbitraryMap => "PAM",
+    }
+}
+
+/// Save As dialog, picks the target directory, file name, format and the
+/// per-format encoder options.
+pub struct SaveAsDialog {
+    open: bool,
+    dir: PathBuf,
+    dir_edit: String,
+    subdirs: Vec<PathBuf>,
+    file_name: String,
+    options: EncodeOptions,
+    /// the ICC profile of the image is written along, when it has one.
+    embed_icc: bool,
+    error: Option<String>,
+}
+
+impl SaveAsDialog {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            open: false,
+            dir_edit: dir.display().to_string(),
+            dir,
+            subdirs: vec![],
+            file_name: String::new(),
+            options: EncodeOptions::Png {
+                compression: CompressionType::Default,
+                filter: FilterType::Adaptive,
+            },
+            embed_icc: true,
+            error: None,
+        }
+    }
+
+    /// Open the dialog prefilled with `path` and its format when those are known.
+    pub fn open(&mut self, path: Option<&Path>, fmt: ImageFormat) {
+        if let Some(dir) = path.and_then(Path::parent).filter(|p| p.is_dir()) {
+            self.set_dir(dir.to_path_buf());
+        } else {
+            self.set_dir(self.dir.clone());
+        }
+        if let Some(options) = EncodeOptions::default_for(fmt) {
+            if options.format() != self.options.format() {
+                self.options = options;
+            }
+        }
+        self.file_name = path
+            .and_then(Path::file_stem)
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "image".to_owned());
+        self.set_extension();
+        self.error = None;
+        self.open = true;
+    }
+
+    /// Report a failed save back into the still opened dialog.
+    pub fn set_error(&mut self, err: String) {
+        self.error = Some(err);
+        self.open = true;
+    }
+
+    fn set_dir(&mut self, dir: PathBuf) {
+        self.subdirs = list_subdirs(&dir);
+        self.dir_edit = dir.display().to_string();
+        self.dir = dir;
+    }
+
+    fn set_extension(&mut self) {
+        let stem = Path::new(&self.file_name)
+            .file_stem()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let ext = self.options.format().extensions_str()[0];
+        self.file_name = format!("{stem}.{ext}");
+    }
+
+    /// Draw the dialog if it is open, returning the target path, encoder
+    /// options and whether to embed the ICC profile once the user confirms.
+    pub fn show(&mut self, ctx: &Context) -> Option<(PathBuf, EncodeOptions, bool)> {
+        if !self.open {
+            return None;
+        }
+        let mut open = self.open;
+        let mut confirmed = false;
+        let mut cancelled = false;
+        let mut navigate = None;
+
+        Window::new(format!("{} Save As", ButtonKind::SaveAs))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_size(Vec2::new(420.0, 360.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("\u{2B06}")
+                        .on_hover_text("Parent directory")
+                        .clicked()
+                    {
+                        navigate = self.dir.parent().map(Path::to_path_buf);
+                    }
+                    let edit = ui
+                        .add(TextEdit::singleline(&mut self.dir_edit).desired_width(f32::INFINITY));
+                    if edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                        navigate = Some(PathBuf::from(&self.dir_edit));
+                    }
+                });
+                ScrollArea::vertical()
+                    .id_source("save_as_dirs")
+                    .max_height(160.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for dir in &self.subdirs {
+                            let name = dir
+                                .file_name()
+                                .map(|n| n.to_string_lossy().into_owned())
+                                .unwrap_or_default();
+                            if ui
+                                .selectable_label(false, format!("\u{1F5C0} {name}"))
+                                .double_clicked()
+                            {
+                                navigate = Some(dir.clone());
+                            }
+                        }
+                    });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("File name");
+                    ui.text_edit_singleline(&mut self.file_name);
+                });
+                let current = self.options.format();
+                ComboBox::from_label("Format")
+                    .selected_text(format!("{current:?}"))
+                    .show_ui(ui, |ui| {
+                        for fmt in ENCODABLE_FORMATS {
+                            if ui
+                                .selectable_label(current == *fmt, format!("{fmt:?}"))
+                                .clicked()
+                            {
+                                if let Some(options) = EncodeOptions::default_for(*fmt) {
+                                    self.options = options;
+                                }
+                            }
+                        }
+                    });
+                if current != self.options.format() {
+                    self.set_extension();
+                }
+                self.options.ui(ui);
+                let fmt = self.options.format();
+                ui.add_enabled(
+                    icc::can_embed(fmt),
+                    Checkbox::new(&mut self.embed_icc, "Embed ICC profile"),
+                )
+                .on_hover_text("Unchecked strips the profile of the image")
+                .on_disabled_hover_text(format!("{fmt:?} is written without a profile"));
+                ui.separator();
+                if self.dir.join(&self.file_name).exists() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "File exists and will be overwritten",
+                    );
+                }
+                if let Some(err) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+                ui.horizontal(|ui| {
+                    let valid = !self.file_name.trim().is_empty();
+                    if ui.add_enabled(valid, Button::new("Save")).clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+
+        if let Some(dir) = navigate {
+            if dir.is_dir() {
+                self.set_dir(dir);
+            } else {
+                self.dir_edit = self.dir.display().to_string();
+            }
+        }
+        self.open = open && !cancelled && !confirmed;
+        confirmed.then(|| {
+            let embed_icc = self.embed_icc && icc::can_embed(self.options.format());
+            (
+                self.dir.join(self.file_name.trim()),
+                self.options,
+                embed_icc,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ColorType, GrayImage, Luma, Rgba, Rgba32FImage, RgbaImage};
+
+    use super::*;
+
+    fn rgba() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 2, |x, y| {
+            Rgba([x as u8 * 60, y as u8 * 200, 30, 128])
+        }))
+    }
+
+    /// Black on the left column, white on the right one.
+    fn black_and_white() -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(2, 2, |x, _| Luma([x as u8 * 255])))
+    }
+
+    fn pnm(subtype: PnmSubtype) -> EncodeOptions {
+        EncodeOptions::Pnm { subtype }
+    }
+
+    #[test]
+    fn converts_to_what_the_encoder_takes() {
+        let rgba16 = DynamicImage::ImageRgba16(rgba().to_rgba16());
+        let gray_alpha = DynamicImage::ImageLumaA8(rgba().to_luma_alpha8());
+        let float = DynamicImage::ImageRgba32F(Rgba32FImage::new(1, 1));
+        let jpeg = EncodeOptions::default_for(ImageFormat::Jpeg).unwrap();
+        let png = EncodeOptions::default_for(ImageFormat::Png).unwrap();
+        let cases = [
+            (jpeg, &rgba(), ColorType::Rgb8),
+            (jpeg, &gray_alpha, ColorType::L8),
+            (EncodeOptions::Bmp, &gray_alpha, ColorType::La8),
+            (EncodeOptions::Ico, &gray_alpha, ColorType::Rgba8),
+            (EncodeOptions::Tiff, &rgba16, ColorType::Rgba16),
+            (EncodeOptions::Tiff, &gray_alpha, ColorType::Rgba8),
+            (EncodeOptions::Gif, &gray_alpha, ColorType::Rgba8),
+            (EncodeOptions::Farbfeld, &rgba(), ColorType::Rgba16),
+            (EncodeOptions::OpenExr, &rgba(), ColorType::Rgba32F),
+            (png, &float, ColorType::Rgba16),
+            (png, &gray_alpha, ColorType::La8),
+            (
+                pnm(PnmSubtype::Pixmap(SampleEncoding::Binary)),
+                &rgba16,
+                ColorType::Rgb16,
+            ),
+            (
+                pnm(PnmSubtype::Graymap(SampleEncoding::Ascii)),
+                &rgba(),
+                ColorType::L8,
+            ),
+            (pnm(PnmSubtype::ArbitraryMap), &float, ColorType::Rgba16),
+        ];
+        for (options, image, color) in cases {
+            assert_eq!(options.convert(image).color(), color, "{options:?}");
+        }
+    }
+
+    #[test]
+    fn tiff_keeps_16_bit_gray() {
+        let gray16 = DynamicImage::ImageLuma16(rgba().to_luma16());
+        let converted = EncodeOptions::Tiff.convert(&gray16);
+        assert_eq!(converted.color(), ColorType::L16);
+        assert_eq!(converted.as_bytes(), gray16.as_bytes());
+    }
+
+    #[test]
+    fn bitmaps_keep_black_and_white() {
+        for encoding in [SampleEncoding::Binary, SampleEncoding::Ascii] {
+            let options = pnm(PnmSubtype::Bitmap(encoding));
+            let converted = options.convert(&black_and_white());
+            assert_eq!(converted.to_luma8(), black_and_white().to_luma8());
+            let bytes = options.encode(&converted).unwrap();
+            let decoded = image::load_from_memory_with_format(&bytes, ImageFormat::Pnm)
+                .unwrap()
+                .to_luma8();
+            assert_eq!(decoded, black_and_white().to_luma8(), "{encoding:?}");
+        }
+    }
+
+    #[test]
+    fn encodes_every_format() {
+        for fmt in ENCODABLE_FORMATS {
+            let options = EncodeOptions::default_for(*fmt).unwrap();
+            assert_eq!(options.format(), *fmt);
+            // opaque, the PAM decoder of `image` does not read alpha.
+            let rgb = DynamicImage::ImageRgb8(rgba().to_rgb8());
+            let bytes = options.encode(&options.convert(&rgb)).unwrap();
+            let decoded = image::load_from_memory_with_format(&bytes, *fmt).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (4, 2), "{fmt:?}");
+        }
+        assert_eq!(EncodeOptions::default_for(ImageFormat::Dds), None);
+    }
+
+    #[test]
+    fn pnm_subtypes_are_detected() {
+        use SampleEncoding::{Ascii, Binary};
+        assert_eq!(
+            pnm_subtype_from_magic(*b"P1"),
+            Some(PnmSubtype::Bitmap(Ascii))
+        );
+        assert_eq!(
+            pnm_subtype_from_magic(*b"P5"),
+            Some(PnmSubtype::Graymap(Binary))
+        );
+        assert_eq!(
+            pnm_subtype_from_magic(*b"P7"),
+            Some(PnmSubtype::ArbitraryMap)
+        );
+        assert_eq!(pnm_subtype_from_magic(*b"P8"), None);
+        assert_eq!(
+            pnm_subtype_from_extension(Path::new("a.PPM")),
+            Some(PnmSubtype::Pixmap(Binary))
+        );
+        assert_eq!(pnm_subtype_from_extension(Path::new("a.pnm")), None);
+        assert_eq!(pnm_subtype_from_extension(Path::new("pbm")), None);
+    }
+
+    #[test]
+    fn overwriting_keeps_the_pnm_subtype() {
+        let path = std::env::temp_dir().join(format!("rziv-save-test-{}.ppm", std::process::id()));
+        fs::write(&path, b"P2\n1 1\n255\n0\n").unwrap();
+        let options = EncodeOptions::default_for_file(ImageFormat::Pnm, &path);
+        fs::remove_file(&path).ok();
+        assert_eq!(
+            options,
+            Some(pnm(PnmSubtype::Graymap(SampleEncoding::Ascii)))
+        );
+        let missing = path.with_extension("pbm");
+        assert_eq!(
+            EncodeOptions::default_for_file(ImageFormat::Pnm, &missing),
+            Some(pnm(PnmSubtype::Bitmap(SampleEncoding::Binary)))
+        );
+    }
+}
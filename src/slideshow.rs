@@ -0,0 +1,333 @@
+use std::time::Duration;
+
+use eframe::{
+    egui::{self, Context, Slider, Ui},
+    emath::Align2,
+    epaint::Vec2,
+};
+
+use crate::button::ButtonKind;
+
+/// Seconds without pointer movement before the controls are hidden.
+const CONTROLS_HIDE_AFTER: f64 = 2.0;
+/// Shortest interval, shorter ones would advance on nearly every frame. The
+/// command line and the interval slider take the same range.
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone)]
+pub struct SlideshowOptions {
+    pub interval: Duration,
+    pub shuffle: bool,
+    /// start over after the last image instead of stopping.
+    pub repeat: bool,
+    pub fullscreen: bool,
+    /// length of the crossfade between two images, zero to cut.
+    pub crossfade: Duration,
+}
+
+impl Default for SlideshowOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            shuffle: false,
+            repeat: true,
+            fullscreen: false,
+            crossfade: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Advances the shown image on a timer, which only runs once the shown
+/// image is loaded so slow images are not skipped.
+#[derive(Debug)]
+pub struct Slideshow {
+    pub options: SlideshowOptions,
+    running: bool,
+    paused: bool,
+    /// `egui` time the shown image was ready at.
+    shown_at: Option<f64>,
+    /// images left in this shuffled pass, popped from the back, `None`
+    /// until the pass is shuffled.
+    order: Option<Vec<usize>>,
+    /// length of the list `order` was shuffled for.
+    order_len: usize,
+    /// running with the fullscreen option, as of the last sync.
+    wants_fullscreen: bool,
+    /// fullscreen was turned on by the slideshow, and is undone on stop.
+    set_fullscreen: bool,
+}
+
+impl Slideshow {
+    pub fn new(options: SlideshowOptions) -> Self {
+        Self {
+            options,
+            running: false,
+            paused: false,
+            shown_at: None,
+            order: None,
+            order_len: 0,
+            wants_fullscreen: false,
+            set_fullscreen: false,
+        }
+    }
+
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn start(&mut self) {
+        self.running = true;
+        self.paused = false;
+        self.shown_at = None;
+        self.order = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.paused = false;
+    }
+
+    pub fn toggle(&mut self) {
+        if self.running {
+            self.stop();
+        } else {
+            self.start();
+        }
+    }
+
+    /// Index of the image to show once the interval of the shown one
+    /// passed, stops at the end of the list unless repeating.
+    pub fn update(
+        &mut self,
+        ctx: &Context,
+        len: usize,
+        shown: usize,
+        ready: bool,
+    ) -> Option<usize> {
+        if !self.running || self.paused || len == 0 {
+            return None;
+        }
+        if !ready {
+            self.shown_at = None;
+            return None;
+        }
+        let now = ctx.input(|i| i.time);
+        let shown_at = *self.shown_at.get_or_insert(now);
+        let left = self.options.interval.as_secs_f64() - (now - shown_at);
+        if left > 0.0 {
+            ctx.request_repaint_after(Duration::from_secs_f64(left));
+            return None;
+        }
+        self.shown_at = None;
+        let next = self.next(len, shown);
+        if next.is_none() {
+            self.stop();
+        }
+        next
+    }
+
+    /// The image [`Slideshow::update`] switches to next, so it can be
+    /// decoded ahead of time.
+    pub fn upcoming(&self, len: usize, shown: usize) -> Option<usize> {
+        if !self.running {
+            return None;
+        }
+        if self.options.shuffle {
+            self.order.as_ref()?.last().copied()
+        } else if shown + 1 < len {
+            Some(shown + 1)
+        } else {
+            self.options.repeat.then_some(0)
+        }
+    }
+
+    fn next(&mut self, len: usize, shown: usize) -> Option<usize> {
+        if !self.options.shuffle {
+            return self.upcoming(len, shown);
+        }
+        if self.order_len != len {
+            self.order = None;
+        }
+        let order = self.order.get_or_insert_with(|| shuffled(len, shown));
+        self.order_len = len;
+        if order.is_empty() && self.options.repeat {
+            *order = shuffled(len, shown);
+        }
+        let next = order.pop();
+        // shuffle the next pass ahead so `upcoming` knows its first image.
+        if order.is_empty() && self.options.repeat {
+            *order = shuffled(len, next.unwrap_or(shown));
+        }
+        next
+    }
+
+    /// Enter fullscreen when starting if asked to, and leave it again once
+    /// stopped when the slideshow was the one entering it. Only acts when
+    /// that changes, so fullscreen can still be toggled meanwhile.
+    pub fn sync_fullscreen(&mut self, frame: &mut eframe::Frame) {
+        let want = self.running && self.options.fullscreen;
+        if want == self.wants_fullscreen {
+            return;
+        }
+        self.wants_fullscreen = want;
+        if want && !frame.info().window_info.fullscreen {
+            frame.set_fullscreen(true);
+            self.set_fullscreen = true;
+        } else if !want && self.set_fullscreen {
+            frame.set_fullscreen(false);
+            self.set_fullscreen = false;
+        }
+    }
+
+    /// Controls over the top of `ui` while running, hidden once the pointer
+    /// rests for a while.
+    pub fn controls_ui(&mut self, ui: &Ui) {
+        if !self.running {
+            return;
+        }
+        let ctx = ui.ctx();
+        let rested = ctx.input(|i| i.pointer.time_since_last_movement());
+        if rested > CONTROLS_HIDE_AFTER && !self.paused {
+            return;
+        }
+        // paused controls stay up, `rested` then grows past the delay.
+        if rested < CONTROLS_HIDE_AFTER {
+            ctx.request_repaint_after(Duration::from_secs_f64(CONTROLS_HIDE_AFTER - rested));
+        }
+        egui::Area::new("iv_slideshow_controls")
+            .fixed_pos(ui.max_rect().center_top() + Vec2::new(0.0, 8.0))
+            .pivot(Align2::CENTER_TOP)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| self.options_ui(ui));
+                });
+            });
+    }
+
+    fn options_ui(&mut self, ui: &mut Ui) {
+        let kind = if self.paused {
+            ButtonKind::Play
+        } else {
+            ButtonKind::Pause
+        };
+        if ui.button(kind.name()).clicked() {
+            self.paused = !self.paused;
+            self.shown_at = None;
+        }
+        if ui
+            .button(ButtonKind::Stop.name())
+            .on_hover_text("Stop the slideshow")
+            .clicked()
+        {
+            self.stop();
+        }
+        let shuffle = ui
+            .selectable_label(self.options.shuffle, ButtonKind::Random.name())
+            .on_hover_text("Shuffle");
+        if shuffle.clicked() {
+            self.options.shuffle = !self.options.shuffle;
+            self.order = None;
+        }
+        if ui
+            .selectable_label(self.options.fullscreen, ButtonKind::FullScreen.name())
+            .on_hover_text("Fullscreen")
+            .clicked()
+        {
+            self.options.fullscreen = !self.options.fullscreen;
+        }
+        ui.checkbox(&mut self.options.repeat, "Loop");
+        let mut interval = self.options.interval.as_secs_f32();
+        let range = MIN_INTERVAL.as_secs_f32()..=MAX_INTERVAL.as_secs_f32();
+        let slider = Slider::new(&mut interval, range)
+            .logarithmic(true)
+            .suffix(" s")
+            .text("Interval");
+        if ui.add(slider).changed() {
+            self.options.interval = Duration::from_secs_f32(interval);
+        }
+        let mut crossfade = self.options.crossfade.as_secs_f32();
+        let slider = Slider::new(&mut crossfade, 0.0..=2.0)
+            .suffix(" s")
+            .text("Crossfade");
+        if ui.add(slider).changed() {
+            self.options.crossfade = Duration::from_secs_f32(crossfade);
+        }
+    }
+}
+
+/// Every index but `shown`, in random order.
+fn shuffled(len: usize, shown: usize) -> Vec<usize> {
+    let mut order = (0..len).filter(|idx| *idx != shown).collect::<Vec<_>>();
+    fastrand::shuffle(&mut order);
+    order
+}
+
+/// Parse a duration like `5s`, `500ms`, `2m` or plain seconds `1.5`.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let (value, scale) = if let Some(ms) = text.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = text.strip_suffix('s') {
+        (s, 1.0)
+    } else if let Some(m) = text.strip_suffix('m') {
+        (m, 60.0)
+    } else {
+        (text, 1.0)
+    };
+    let value = value
+        .trim()
+        .parse::<f64>()
+        .map_err(|err| format!("invalid duration {text:?} - (Reason: {err})"))?;
+    Duration::try_from_secs_f64(value * scale)
+        .map_err(|err| format!("invalid duration {text:?} - (Reason: {err})"))
+}
+
+/// Parse a slideshow interval like [`parse_duration`], from 100ms to 10m.
+pub fn parse_interval(text: &str) -> Result<Duration, String> {
+    let interval = parse_duration(text)?;
+    if interval < MIN_INTERVAL {
+        return Err(format!(
+            "slideshow interval {text:?} is shorter than {}ms",
+            MIN_INTERVAL.as_millis()
+        ));
+    }
+    if interval > MAX_INTERVAL {
+        return Err(format!(
+            "slideshow interval {text:?} is longer than {}m",
+            MAX_INTERVAL.as_secs() / 60
+        ));
+    }
+    Ok(interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("5s"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration(" 1.5 "), Ok(Duration::from_millis(1500)));
+        assert!(parse_duration("fast").is_err());
+        assert!(parse_duration("-1s").is_err());
+    }
+
+    #[test]
+    fn bounds_intervals() {
+        assert_eq!(parse_interval("100ms"), Ok(MIN_INTERVAL));
+        assert_eq!(parse_interval("10m"), Ok(MAX_INTERVAL));
+        assert!(parse_interval("99ms").is_err());
+        assert!(parse_interval("11m").is_err());
+    }
+
+    #[test]
+    fn shuffles_every_other_index() {
+        let mut order = shuffled(5, 2);
+        order.sort_unstable();
+        assert_eq!(order, [0, 1, 3, 4]);
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{mpsc::TryRecvError, Arc},
+};
+
+use eframe::{
+    egui::{Context, TextureHandle, TextureOptions},
+    epaint::ColorImage,
+};
+use image::{DynamicImage, RgbaImage};
+
+use crate::{
+    cache::CacheKey,
+    loader::{self, JobHandle, WorkerPool},
+    orientation::Orientation,
+    thumbnail_cache::{self, ThumbSize},
+};
+
+/// Thumbnails kept before the least recently drawn are dropped.
+const MAX_THUMBNAILS: usize = 2048;
+/// Added to the priority of thumbnail jobs so decoding shown images wins.
+const THUMBNAIL_PRIORITY: usize = 1 << 16;
+
+type ThumbResult = Result<ColorImage, String>;
+
+/// Where a thumbnail is generated from.
+pub enum ThumbSource {
+    Path(PathBuf),
+    Bytes(Arc<[u8]>),
+    /// decoded pixels, with the orientation their EXIF metadata asks for.
+    Pixels(Arc<DynamicImage>, Orientation),
+}
+
+pub enum ThumbPoll {
+    Pending,
+    Ready(TextureHandle),
+    Failed(String),
+}
+
+enum ThumbState {
+    Loading(JobHandle<ThumbResult>),
+    Ready(TextureHandle),
+    Failed(String),
+}
+
+struct Entry {
+    state: ThumbState,
+    last_used: u64,
+}
+
+/// Small textures of the listed images, generated on the worker pool only
+/// for the images that are actually drawn.
+#[derive(Default)]
+pub struct Thumbnails {
+    /// keyed by whether the EXIF orientation is applied too.
+    entries: HashMap<(CacheKey, ThumbSize, bool), Entry>,
+    frame: u64,
+}
+
+impl Thumbnails {
+    /// Thumbnail of `key`, queueing its generation from `source` on the
+    /// first request. `priority` orders it against the other thumbnails,
+    /// `apply_exif` turns it upright like the shown image.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get(
+        &mut self,
+        ctx: &Context,
+        pool: &WorkerPool,
+        key: &CacheKey,
+        size: ThumbSize,
+        apply_exif: bool,
+        priority: usize,
+        source: impl FnOnce() -> Option<ThumbSource>,
+    ) -> ThumbPoll {
+        let frame = self.frame;
+        let key = (key.clone(), size, apply_exif);
+        if !self.entries.contains_key(&key) {
+            let Some(source) = source() else {
+                return ThumbPoll::Pending;
+            };
+            let job = pool.submit(ctx, THUMBNAIL_PRIORITY + priority, move |state| {
+                let (image, orientation) = match source {
+                    // the shared cache only holds upright thumbnails.
+                    ThumbSource::Path(path) => {
+                        if let Some(thumb) = apply_exif
+                            .then(|| thumbnail_cache::load(&path, size))
+                            .flatten()
+                        {
+                            return Some(Ok(color_image(thumb.into_rgba8())));
+                        }
+                        let bytes = match loader::read_with_progress(&path, state) {
+                            Ok(bytes) => bytes?,
+                            Err(err) => {
+                                return Some(Err(format!(
+                                    "Failed to read content of file: {} - (Reason: {err})",
+                                    path.display()
+                                )))
+                            }
+                        };
+                        let image = match image::load_from_memory(&bytes) {
+                            Ok(image) => image,
+                            Err(err) => {
+                                return Some(Err(format!(
+                                    "Failed to decode image - (Reason: {err})"
+                                )))
+                            }
+                        };
+                        if !apply_exif {
+                            let thumb = thumbnail_image(&image, size, Orientation::default());
+                            return Some(Ok(color_image(thumb)));
+                        }
+                        let thumb = thumbnail_image(&image, size, Orientation::from_exif(&bytes));
+                        if let Err(err) = thumbnail_cache::store(&path, size, &thumb) {
+                            log::error!(
+                                "Failed to store thumbnail of {} - (Reason: {err})",
+                                path.display()
+                            );
+                        }
+                        return Some(Ok(color_image(thumb)));
+                    }
+                    ThumbSource::Bytes(bytes) => match image::load_from_memory(&bytes) {
+                        Ok(image) => (Arc::new(image), Orientation::from_exif(&bytes)),
+                        Err(err) => {
+                            return Some(Err(format!("Failed to decode image - (Reason: {err})")))
+                        }
+                    },
+                    ThumbSource::Pixels(image, orientation) => (image, orientation),
+                };
+                let orientation = if apply_exif {
+                    orientation
+                } else {
+                    Orientation::default()
+                };
+                (!state.is_cancelled())
+                    .then(|| Ok(color_image(thumbnail_image(&image, size, orientation))))
+            });
+            self.entries.insert(
+                key.clone(),
+                Entry {
+                    state: ThumbState::Loading(job),
+                    last_used: frame,
+                },
+            );
+        }
+        let entry = self.entries.get_mut(&key).expect("inserted above");
+        entry.last_used = frame;
+        if let ThumbState::Loading(job) = &entry.state {
+            match job.try_recv() {
+                Ok(Ok(color_image)) => {
+                    let name = format!("thumbnail-{key:?}");
+                    let texture = ctx.load_texture(name, color_image, TextureOptions::LINEAR);
+                    entry.state = ThumbState::Ready(texture);
+                }
+                Ok(Err(err)) => entry.state = ThumbState::Failed(err),
+                Err(TryRecvError::Empty) => job.set_priority(THUMBNAIL_PRIORITY + priority),
+                Err(TryRecvError::Disconnected) => {
+                    entry.state = ThumbState::Failed("thumbnail job is gone".to_owned())
+                }
+            }
+        }
+        match &entry.state {
+            ThumbState::Loading(_) => ThumbPoll::Pending,
+            ThumbState::Ready(texture) => ThumbPoll::Ready(texture.clone()),
+            ThumbState::Failed(err) => ThumbPoll::Failed(err.clone()),
+        }
+    }
+
+    /// Cancel the jobs of thumbnails not drawn this frame, and drop the
+    /// least recently drawn ones once there are too many.
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+        self.entries.retain(|_, entry| {
+            entry.last_used == frame || !matches!(entry.state, ThumbState::Loading(_))
+        });
+        if self.entries.len() > MAX_THUMBNAILS {
+            let mut used = self
+                .entries
+                .values()
+                .map(|entry| entry.last_used)
+                .collect::<Vec<_>>();
+            used.sort_unstable();
+            let cutoff = used[self.entries.len() - MAX_THUMBNAILS];
+            self.entries.retain(|_, entry| entry.last_used >= cutoff);
+        }
+        self.frame += 1;
+    }
+}
+
+impl std::fmt::Debug for Thumbnails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Thumbnails")
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+/// Downscale `image` to fit in `size`, smaller images are kept as they are,
+/// then apply `orientation`.
+fn thumbnail_image(image: &DynamicImage, size: ThumbSize, orientation: Orientation) -> RgbaImage {
+    let side = size.side();
+    let thumb = if image.width() <= side && image.height() <= side {
+        image.to_rgba8()
+    } else {
+        image.thumbnail(side, side).into_rgba8()
+    };
+    if orientation.is_identity() {
+        thumb
+    } else {
+        orientation
+            .apply_to_image(DynamicImage::ImageRgba8(thumb))
+            .into_rgba8()
+    }
+}
+
+fn color_image(rgba: RgbaImage) -> ColorImage {
+    let size = [rgba.width() as usize, rgba.height() as usize];
+    ColorImage::from_rgba_unmultiplied(size, rgba.as_raw())
+}
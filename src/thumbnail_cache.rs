@@ -0,0 +1,199 @@
+use std::{
+    io::{BufWriter, Cursor, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use image::{DynamicImage, RgbaImage};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+
+/// Characters left as is in file URIs, same as GLib does.
+const URI_PATH: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@')
+    .remove(b'/');
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThumbSize {
+    Normal,
+    Large,
+}
+
+impl ThumbSize {
+    /// Longest side of the thumbnail, in pixels.
+    pub fn side(self) -> u32 {
+        match self {
+            Self::Normal => 128,
+            Self::Large => 256,
+        }
+    }
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Large => "large",
+        }
+    }
+}
+
+/// `$XDG_CACHE_HOME/thumbnails`, falling back to `~/.cache/thumbnails`, as
+/// in the freedesktop thumbnail specification so the cache is shared with
+/// file managers and other viewers.
+fn thumbnails_dir() -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache.join("thumbnails"))
+}
+
+/// Canonical `file://` URI of `path`, the thumbnail is named after its MD5.
+fn file_uri(path: &Path) -> Option<String> {
+    let path = std::fs::canonicalize(path).ok()?;
+    let path = path.to_str()?;
+    Some(format!(
+        "file://{}",
+        percent_encoding::utf8_percent_encode(path, URI_PATH)
+    ))
+}
+
+fn thumbnail_path(uri: &str, size: ThumbSize) -> Option<PathBuf> {
+    let name = format!("{:x}.png", md5::compute(uri.as_bytes()));
+    Some(thumbnails_dir()?.join(size.dir_name()).join(name))
+}
+
+fn mtime(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// Cached thumbnail of the file at `path`, if there is one that is still
+/// valid for the current modification time of the file.
+pub fn load(path: &Path, size: ThumbSize) -> Option<DynamicImage> {
+    let uri = file_uri(path)?;
+    let thumb_path = thumbnail_path(&uri, size)?;
+    let bytes = std::fs::read(thumb_path).ok()?;
+    let reader = png::Decoder::new(Cursor::new(&bytes)).read_info().ok()?;
+    let info = reader.info();
+    let text = |key: &str| {
+        info.uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == key)
+            .map(|chunk| chunk.text.as_str())
+    };
+    if text("Thumb::URI") != Some(uri.as_str()) {
+        return None;
+    }
+    let thumb_mtime = text("Thumb::MTime")?.parse::<u64>().ok()?;
+    if Some(thumb_mtime) != mtime(path) {
+        return None;
+    }
+    image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).ok()
+}
+
+/// Write `thumb` of the file at `path` into the cache, through a temporary
+/// file so other readers never see it half written.
+pub fn store(path: &Path, size: ThumbSize, thumb: &RgbaImage) -> anyhow::Result<()> {
+    let uri = file_uri(path).ok_or_else(|| anyhow::anyhow!("Path has no file URI"))?;
+    let thumb_path = thumbnail_path(&uri, size)
+        .ok_or_else(|| anyhow::anyhow!("No thumbnail cache directory"))?;
+    let dir = thumb_path.parent().expect("thumbnail path has a directory");
+    if path.starts_with(dir.parent().expect("size directory has a parent")) {
+        // thumbnails of thumbnails are not cached.
+        return Ok(());
+    }
+    let mtime = mtime(path).ok_or_else(|| anyhow::anyhow!("File has no modification time"))?;
+    let file_size = std::fs::metadata(path)?.len();
+
+    create_private_dir(dir)?;
+    let tmp_path = thumb_path.with_extension(format!("rziv-{}.tmp", std::process::id()));
+    let result = (|| -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(create_private_file(&tmp_path)?);
+        let mut encoder = png::Encoder::new(&mut writer, thumb.width(), thumb.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.add_text_chunk("Thumb::URI".to_owned(), uri)?;
+        encoder.add_text_chunk("Thumb::MTime".to_owned(), mtime.to_string())?;
+        encoder.add_text_chunk("Thumb::Size".to_owned(), file_size.to_string())?;
+        encoder.add_text_chunk("Software".to_owned(), "rziv".to_owned())?;
+        encoder.write_header()?.write_image_data(thumb.as_raw())?;
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&tmp_path, &thumb_path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        std::fs::remove_file(&tmp_path).ok();
+    }
+    result
+}
+
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)
+}
+
+fn create_private_file(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_uris_are_percent_encoded() {
+        let dir = std::env::temp_dir().join(format!("rziv-uri-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a b#c(1).png");
+        std::fs::write(&path, b"").unwrap();
+        let uri = file_uri(&path);
+        std::fs::remove_dir_all(&dir).ok();
+
+        let dir = std::fs::canonicalize(std::env::temp_dir()).unwrap();
+        let expected = format!(
+            "file://{}/rziv-uri-test-{}/a%20b%23c(1).png",
+            dir.display(),
+            std::process::id()
+        );
+        assert_eq!(uri, Some(expected));
+        assert_eq!(file_uri(Path::new("/does/not/exist")), None);
+    }
+
+    #[test]
+    fn thumbnails_are_named_after_the_uri_md5() {
+        // example of the freedesktop thumbnail specification.
+        let Some(path) = thumbnail_path("file:///home/jens/photos/me.png", ThumbSize::Large) else {
+            return;
+        };
+        assert!(path.ends_with("thumbnails/large/c6ee772d9e49320e97ec29a7eb5b1697.png"));
+    }
+}